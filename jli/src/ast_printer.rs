@@ -1,8 +1,8 @@
 use crate::expr::{
-    Assign, Binary, Call, Expr, Get, Grouping, Literal, Logical, Set, Super, This, Unary, Variable,
-    Visitor, VisitorResult,
+    Assign, Binary, Call, Comma, Conditional, Expr, Get, Grouping, Index, Lambda, List, Literal,
+    Logical, Map, Set, SetIndex, Stringify, Super, This, Unary, Variable, Visitor, VisitorResult,
 };
-use crate::token::{Token, TokenType};
+use crate::token::{Span, Token, TokenType};
 use std::rc::Rc;
 
 pub struct AstPrinter;
@@ -20,6 +20,17 @@ impl Visitor<VisitorResult> for AstPrinter {
         todo!();
     }
 
+    fn visit_comma_expr(&mut self, expr: &Comma) -> VisitorResult {
+        self.parenthesize(",", &[&*expr.left, &*expr.right])
+    }
+
+    fn visit_conditional_expr(&mut self, expr: &Conditional) -> VisitorResult {
+        self.parenthesize(
+            "?:",
+            &[&*expr.condition, &*expr.then_branch, &*expr.else_branch],
+        )
+    }
+
     fn visit_get_expr(&mut self, _expr: &Get) -> VisitorResult {
         todo!();
     }
@@ -28,6 +39,27 @@ impl Visitor<VisitorResult> for AstPrinter {
         self.parenthesize("group", &[&*expr.expression])
     }
 
+    fn visit_index_expr(&mut self, expr: &Index) -> VisitorResult {
+        self.parenthesize("index", &[&*expr.object, &*expr.index])
+    }
+
+    fn visit_lambda_expr(&mut self, expr: &Lambda) -> VisitorResult {
+        let params: Vec<&str> = expr
+            .params
+            .positional
+            .iter()
+            .map(|p| &p.name)
+            .chain(&expr.params.rest)
+            .map(|name| name.lexeme.as_str())
+            .collect();
+        Ok(Rc::new(format!("(fun ({}))", params.join(" "))))
+    }
+
+    fn visit_list_expr(&mut self, expr: &List) -> VisitorResult {
+        let elements: Vec<&dyn Expr> = expr.elements.iter().map(|e| &**e).collect();
+        self.parenthesize("list", &elements)
+    }
+
     fn visit_literal_expr(&mut self, expr: &Literal) -> VisitorResult {
         Ok(Rc::new(format!("{}", expr.value)))
     }
@@ -36,10 +68,27 @@ impl Visitor<VisitorResult> for AstPrinter {
         todo!();
     }
 
+    fn visit_map_expr(&mut self, expr: &Map) -> VisitorResult {
+        let entries: Vec<&dyn Expr> = expr
+            .entries
+            .iter()
+            .flat_map(|(k, v)| [&**k, &**v])
+            .collect();
+        self.parenthesize("map", &entries)
+    }
+
     fn visit_set_expr(&mut self, _expr: &Set) -> VisitorResult {
         todo!();
     }
 
+    fn visit_setindex_expr(&mut self, expr: &SetIndex) -> VisitorResult {
+        self.parenthesize("set-index", &[&*expr.object, &*expr.index, &*expr.value])
+    }
+
+    fn visit_stringify_expr(&mut self, expr: &Stringify) -> VisitorResult {
+        self.parenthesize("str", &[&*expr.expression])
+    }
+
     fn visit_super_expr(&mut self, _expr: &Super) -> VisitorResult {
         todo!();
     }
@@ -79,10 +128,10 @@ impl AstPrinter {
 pub fn run() {
     let expression = Binary::boxed(
         Unary::boxed(
-            Token::new(TokenType::Minus, "-".to_string(), 1),
+            Token::new(TokenType::Minus, "-".to_string(), 1, Span::new(0, 1, 0, 1)),
             Literal::boxed(Rc::new(123.0)),
         ),
-        Token::new(TokenType::Star, "*".to_string(), 1),
+        Token::new(TokenType::Star, "*".to_string(), 1, Span::new(0, 6, 5, 6)),
         Grouping::boxed(Literal::boxed(Rc::new(45.67))),
     );
     let mut printer = AstPrinter {};
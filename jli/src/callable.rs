@@ -1,18 +1,82 @@
 use crate::environment::Environment;
-use crate::error::Error;
+use crate::error::{Error, Frame};
 use crate::instance::Instance;
 use crate::interpreter::Interpreter;
+use crate::list::List;
 use crate::object::{Nil, Object};
 use crate::stmt::Function;
+use crate::token::Token;
 use crate::Result;
 use std::fmt;
 use std::rc::Rc;
 
 pub type CallResult = Result<Rc<dyn Object>>;
+type NativeBody = dyn Fn(&mut Interpreter, &Token, &[Rc<dyn Object>]) -> CallResult;
 
 pub trait Callable: Object {
-    fn arity(&self) -> usize;
-    fn call(&self, interpreter: &mut Interpreter, arguments: &[Rc<dyn Object>]) -> CallResult;
+    fn arity(&self) -> Arity;
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: &[Rc<dyn Object>],
+    ) -> CallResult;
+
+    /// Like `call`, with some arguments passed by parameter name. Only
+    /// functions declared in Lox know their parameters' names.
+    fn call_named(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: &[Rc<dyn Object>],
+        named: &[(Token, Rc<dyn Object>)],
+    ) -> CallResult {
+        match named.first() {
+            Some((name, _)) => Err(Error::runtime(
+                name,
+                format!("{} doesn't take named arguments.", self),
+            )),
+            None => self.call(interpreter, paren, arguments),
+        }
+    }
+}
+
+/// How many arguments a callable accepts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Arity {
+    pub min: usize,
+    /// `None` when any number of extra arguments is collected.
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    pub fn exactly(count: usize) -> Self {
+        Self {
+            min: count,
+            max: Some(count),
+        }
+    }
+
+    pub fn accepts(&self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+
+/// Formats as e.g. "1 argument", "1 to 2 arguments" or "at least 1 argument".
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{}", max)?,
+            Some(max) => return write!(f, "{} to {} arguments", self.min, max),
+            None => write!(f, "at least {}", self.min)?,
+        }
+        let noun = if self.min == 1 {
+            "argument"
+        } else {
+            "arguments"
+        };
+        write!(f, " {}", noun)
+    }
 }
 
 #[derive(Debug)]
@@ -20,6 +84,7 @@ pub struct LoxFunction {
     declaration: Rc<Function>,
     closure: Rc<Environment>,
     is_initializer: bool,
+    is_anonymous: bool,
 }
 
 impl LoxFunction {
@@ -28,12 +93,93 @@ impl LoxFunction {
             declaration,
             closure,
             is_initializer,
+            is_anonymous: false,
+        }
+    }
+
+    /// Creates a function from a `fun (...) {...}` expression, whose
+    /// declaration is named after the `fun` keyword.
+    pub fn anonymous(declaration: Rc<Function>, closure: Rc<Environment>) -> Self {
+        Self {
+            is_anonymous: true,
+            ..Self::new(declaration, closure, false)
+        }
+    }
+
+    fn name(&self) -> &str {
+        if self.is_anonymous {
+            "anonymous"
+        } else {
+            &self.declaration.name.lexeme
+        }
+    }
+
+    /// Defines the parameters in `environment`, filling in defaults for the
+    /// ones that got no argument.
+    fn bind_arguments(
+        &self,
+        interpreter: &mut Interpreter,
+        environment: &Rc<Environment>,
+        paren: &Token,
+        arguments: &[Rc<dyn Object>],
+        named: &[(Token, Rc<dyn Object>)],
+    ) -> Result<()> {
+        let params = &self.declaration.params;
+        let mut values: Vec<Option<Rc<dyn Object>>> = vec![None; params.positional.len()];
+        for (value, argument) in values.iter_mut().zip(arguments) {
+            *value = Some(argument.clone());
+        }
+        for (name, argument) in named {
+            let position = params
+                .positional
+                .iter()
+                .position(|p| p.name.lexeme == name.lexeme);
+            match position {
+                Some(i) if values[i].is_some() => {
+                    let message = format!("Got more than one value for '{}'.", name.lexeme);
+                    return Err(Error::runtime(name, message));
+                }
+                Some(i) => values[i] = Some(argument.clone()),
+                None => {
+                    let message = format!("{} has no parameter '{}'.", self, name.lexeme);
+                    return Err(Error::runtime(name, message));
+                }
+            }
+        }
+        // Defaults run in order, so they can refer to earlier parameters.
+        for (param, value) in params.positional.iter().zip(values) {
+            let value = match (value, &param.default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => interpreter
+                    .evaluate_in(&**default, environment.clone())
+                    .map_err(|mut e| {
+                    // Defaults run inside the call, like its body.
+                    e.push_frame(self.frame(paren));
+                    e
+                })?,
+                (None, None) => {
+                    let message = format!("Missing argument for '{}'.", param.name.lexeme);
+                    return Err(Error::runtime(paren, message));
+                }
+            };
+            environment.define(&param.name.lexeme, value)?;
+        }
+        if let Some(rest) = &params.rest {
+            let extra = arguments.get(params.positional.len()..).unwrap_or_default();
+            environment.define(&rest.lexeme, Rc::new(List::new(extra.to_vec())))?;
         }
+        Ok(())
+    }
+
+    fn frame(&self, paren: &Token) -> Frame {
+        Frame::function(self.name(), paren.line)
     }
 
     pub fn bind(&self, instance: Rc<Instance>) -> Self {
         let environment = Environment::new_with_enclosing(self.closure.clone());
-        environment.define("this", instance);
+        environment
+            .define("this", instance)
+            .expect("A new environment has no constants.");
         Self::new(
             self.declaration.clone(),
             Rc::new(environment),
@@ -50,22 +196,43 @@ impl PartialEq for LoxFunction {
 
 impl fmt::Display for LoxFunction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "<fn {}>", self.declaration.name.lexeme)
+        write!(f, "<fn {}>", self.name())
     }
 }
 
 impl Object for LoxFunction {}
 
 impl Callable for LoxFunction {
-    fn arity(&self) -> usize {
-        self.declaration.params.len()
+    fn arity(&self) -> Arity {
+        let params = &self.declaration.params;
+        let required = params.positional.iter().filter(|p| p.default.is_none());
+        Arity {
+            min: required.count(),
+            max: match params.rest {
+                Some(_) => None,
+                None => Some(params.positional.len()),
+            },
+        }
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: &[Rc<dyn Object>],
+    ) -> CallResult {
+        self.call_named(interpreter, paren, arguments, &[])
     }
 
-    fn call(&self, interpreter: &mut Interpreter, arguments: &[Rc<dyn Object>]) -> CallResult {
+    fn call_named(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: &[Rc<dyn Object>],
+        named: &[(Token, Rc<dyn Object>)],
+    ) -> CallResult {
         let environment = Rc::new(Environment::new_with_enclosing(self.closure.clone()));
-        for (param, argument) in self.declaration.params.iter().zip(arguments) {
-            environment.define(&param.lexeme, argument.clone());
-        }
+        self.bind_arguments(interpreter, &environment, paren, arguments, named)?;
         let result = interpreter.execute_block(&self.declaration.body, environment);
         match result {
             Ok(()) => {
@@ -82,7 +249,10 @@ impl Callable for LoxFunction {
                     Ok(v)
                 }
             }
-            Err(e) => Err(e),
+            Err(mut e) => {
+                e.push_frame(self.frame(paren));
+                Err(e)
+            }
         }
     }
 }
@@ -112,15 +282,70 @@ impl fmt::Display for NativeFunction {
 }
 
 impl Callable for NativeFunction {
-    fn arity(&self) -> usize {
+    fn arity(&self) -> Arity {
         match self {
-            Self::Clock => 0,
+            Self::Clock => Arity::exactly(0),
         }
     }
 
-    fn call(&self, _: &mut Interpreter, _: &[Rc<dyn Object>]) -> CallResult {
+    fn call(&self, _: &mut Interpreter, _: &Token, _: &[Rc<dyn Object>]) -> CallResult {
         match self {
             Self::Clock => self.clock(),
         }
     }
 }
+
+/// A built-in method bound to the value it was looked up on.
+pub struct NativeMethod {
+    name: &'static str,
+    arity: usize,
+    body: Box<NativeBody>,
+}
+
+impl NativeMethod {
+    pub fn new<F>(name: &'static str, arity: usize, body: F) -> Self
+    where
+        F: Fn(&mut Interpreter, &Token, &[Rc<dyn Object>]) -> CallResult + 'static,
+    {
+        Self {
+            name,
+            arity,
+            body: Box::new(body),
+        }
+    }
+}
+
+impl Object for NativeMethod {}
+
+impl PartialEq for NativeMethod {
+    fn eq(&self, _other: &Self) -> bool {
+        false
+    }
+}
+
+impl fmt::Debug for NativeMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeMethod({})", self.name)
+    }
+}
+
+impl fmt::Display for NativeMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl Callable for NativeMethod {
+    fn arity(&self) -> Arity {
+        Arity::exactly(self.arity)
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: &[Rc<dyn Object>],
+    ) -> CallResult {
+        (self.body)(interpreter, paren, arguments)
+    }
+}
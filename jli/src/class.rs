@@ -1,42 +1,90 @@
-use crate::callable::{CallResult, Callable, LoxFunction};
-use crate::instance::Instance;
+use crate::callable::{Arity, CallResult, Callable, LoxFunction};
+use crate::error::Error;
+use crate::instance::{Instance, InstanceGet};
 use crate::interpreter::Interpreter;
 use crate::object::Object;
+use crate::token::Token;
+use crate::Result;
 use std::collections::HashMap;
 use std::fmt;
+use std::ptr;
 use std::rc::Rc;
 
+pub type Members = HashMap<String, Rc<LoxFunction>>;
+
 #[derive(Debug, PartialEq)]
 pub struct Class {
     pub name: String,
     superclass: Option<Rc<Class>>,
-    methods: HashMap<String, Rc<LoxFunction>>,
+    methods: Members,
+    class_methods: Members,
+    getters: Members,
+    setters: Members,
 }
 
 impl Class {
     pub fn new(
         name: String,
         superclass: Option<Rc<Class>>,
-        methods: HashMap<String, Rc<LoxFunction>>,
+        methods: Members,
+        class_methods: Members,
+        getters: Members,
+        setters: Members,
     ) -> Self {
         Self {
             name,
             superclass,
             methods,
+            class_methods,
+            getters,
+            setters,
         }
     }
 
+    /// Whether this is `other` or inherits from it.
+    pub fn is_subclass_of(&self, other: &Class) -> bool {
+        ptr::eq(self, other)
+            || self
+                .superclass
+                .as_ref()
+                .is_some_and(|sc| sc.is_subclass_of(other))
+    }
+
     pub fn find_method<S: AsRef<str>>(&self, name: S) -> Option<Rc<LoxFunction>> {
-        let name_ref = name.as_ref();
-        if self.methods.contains_key(name_ref) {
-            return self.methods.get(name_ref).cloned();
+        self.find(name.as_ref(), |class| &class.methods)
+    }
+
+    pub fn find_getter(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        self.find(name, |class| &class.getters)
+    }
+
+    pub fn find_setter(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        self.find(name, |class| &class.setters)
+    }
+
+    fn find(&self, name: &str, members: fn(&Class) -> &Members) -> Option<Rc<LoxFunction>> {
+        if let Some(member) = members(self).get(name) {
+            return Some(member.clone());
         } else if let Some(sc) = &self.superclass {
-            return sc.find_method(name);
+            return sc.find(name, members);
         }
         None
     }
 }
 
+/// Looks up the class methods, which are inherited like instance methods.
+impl InstanceGet for Rc<Class> {
+    fn get(&self, _interpreter: &mut Interpreter, name: &Token) -> Result<Rc<dyn Object>> {
+        match self.find(&name.lexeme, |class| &class.class_methods) {
+            Some(method) => Ok(method),
+            None => Err(Error::runtime(
+                name,
+                format!("Undefined property '{}'.", name.lexeme),
+            )),
+        }
+    }
+}
+
 impl Object for Class {}
 impl Object for Rc<Class> {}
 
@@ -47,17 +95,34 @@ impl fmt::Display for Class {
 }
 
 impl Callable for Rc<Class> {
-    fn arity(&self) -> usize {
+    fn arity(&self) -> Arity {
         match self.find_method("init") {
             Some(initializer) => initializer.arity(),
-            None => 0,
+            None => Arity::exactly(0),
         }
     }
 
-    fn call(&self, interpreter: &mut Interpreter, arguments: &[Rc<dyn Object>]) -> CallResult {
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: &[Rc<dyn Object>],
+    ) -> CallResult {
+        self.call_named(interpreter, paren, arguments, &[])
+    }
+
+    fn call_named(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: &[Rc<dyn Object>],
+        named: &[(Token, Rc<dyn Object>)],
+    ) -> CallResult {
         let instance = Rc::new(Instance::new(self.clone()));
         if let Some(initializer) = self.find_method("init") {
-            initializer.bind(instance).call(interpreter, arguments)
+            initializer
+                .bind(instance)
+                .call_named(interpreter, paren, arguments, named)
         } else {
             Ok(instance)
         }
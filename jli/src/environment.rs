@@ -1,14 +1,16 @@
+use crate::error::Error;
 use crate::object::Object;
 use crate::token::Token;
 use crate::Result;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 #[derive(Debug, Default)]
 pub struct Environment {
     pub enclosing: Option<Rc<Environment>>,
     values: RefCell<HashMap<String, Rc<dyn Object>>>,
+    constants: RefCell<HashSet<String>>,
 }
 
 impl Environment {
@@ -25,23 +27,43 @@ impl Environment {
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.get(name)
         } else {
-            Err(format!("Undefined variable {}.", name.lexeme).into())
+            Err(Error::runtime(
+                name,
+                format!("Undefined variable '{}'.", name.lexeme),
+            ))
         }
     }
 
     pub fn assign(&self, name: &Token, value: Rc<dyn Object>) -> Result<()> {
-        if self.values.borrow().contains_key(&name.lexeme) {
+        if self.constants.borrow().contains(&name.lexeme) {
+            Err(Error::runtime(name, "Cannot assign to constant."))
+        } else if self.values.borrow().contains_key(&name.lexeme) {
             self.values.borrow_mut().insert(name.lexeme.clone(), value);
             Ok(())
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.assign(name, value)
         } else {
-            Err(format!("Undefined variable {}.", name.lexeme).into())
+            Err(Error::runtime(
+                name,
+                format!("Undefined variable '{}'.", name.lexeme),
+            ))
         }
     }
 
-    pub fn define(&self, name: &str, value: Rc<dyn Object>) {
+    /// Fails if `name` is a constant here. The resolver catches that for
+    /// locals, but globals can be redeclared.
+    pub fn define(&self, name: &str, value: Rc<dyn Object>) -> Result<()> {
+        if self.constants.borrow().contains(name) {
+            return Err(format!("Cannot redefine constant '{}'.", name).into());
+        }
         self.values.borrow_mut().insert(name.to_string(), value);
+        Ok(())
+    }
+
+    pub fn define_constant(&self, name: &str, value: Rc<dyn Object>) -> Result<()> {
+        self.define(name, value)?;
+        self.constants.borrow_mut().insert(name.to_string());
+        Ok(())
     }
 
     pub fn get_at(&self, distance: usize, name: &str) -> Result<Rc<dyn Object>> {
@@ -56,6 +78,15 @@ impl Environment {
             .insert(name.lexeme.clone(), value);
     }
 
+    /// The outermost environment, which holds the current file's globals.
+    pub fn globals(&self) -> &Self {
+        let mut environment = self;
+        while let Some(enclosing) = &environment.enclosing {
+            environment = enclosing;
+        }
+        environment
+    }
+
     fn ancestor(&self, distance: usize) -> &Self {
         let mut environment = self;
         for _ in 0..distance {
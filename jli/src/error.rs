@@ -1,13 +1,112 @@
 use crate::object::Object;
+use crate::token::Token;
 use std::fmt;
 use std::rc::Rc;
 
 type BoxedError = Box<dyn std::error::Error + Send + Sync + 'static>;
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum Error {
     DynError(BoxedError),
     Return(Rc<dyn Object>),
+    Break,
+    Continue,
+    ParseError {
+        token: Box<Token>,
+        message: String,
+    },
+    ResolveError {
+        token: Box<Token>,
+        message: String,
+    },
+    RuntimeError {
+        token: Box<Token>,
+        message: String,
+        trace: Vec<Frame>,
+    },
+    /// A value raised by a `throw` statement.
+    Throw {
+        token: Box<Token>,
+        value: Rc<dyn Object>,
+        trace: Vec<Frame>,
+    },
+}
+
+/// A Lox function call or module import that a runtime error unwound
+/// through.
+#[derive(Debug)]
+pub struct Frame {
+    pub name: String,
+    pub line: usize,
+}
+
+impl Frame {
+    pub fn function(name: &str, line: usize) -> Self {
+        Self {
+            name: format!("{}()", name),
+            line,
+        }
+    }
+
+    /// The top level of a module, run by an `import` on `line`.
+    pub fn module(path: &str, line: usize) -> Self {
+        Self {
+            name: format!("module '{}'", path),
+            line,
+        }
+    }
+}
+
+impl Error {
+    pub fn parse<S: Into<String>>(token: &Token, message: S) -> Self {
+        Self::ParseError {
+            token: Box::new(token.clone()),
+            message: message.into(),
+        }
+    }
+
+    pub fn resolve<S: Into<String>>(token: &Token, message: S) -> Self {
+        Self::ResolveError {
+            token: Box::new(token.clone()),
+            message: message.into(),
+        }
+    }
+
+    pub fn runtime<S: Into<String>>(token: &Token, message: S) -> Self {
+        Self::RuntimeError {
+            token: Box::new(token.clone()),
+            message: message.into(),
+            trace: vec![],
+        }
+    }
+
+    /// Turns an error raised without location information into a runtime
+    /// error at the given token. Other errors are returned untouched.
+    pub fn at(self, token: &Token) -> Self {
+        match self {
+            Self::DynError(e) => Self::runtime(token, e.to_string()),
+            e => e,
+        }
+    }
+
+    pub fn push_frame(&mut self, frame: Frame) {
+        if let Self::RuntimeError { trace, .. } | Self::Throw { trace, .. } = self {
+            trace.push(frame);
+        }
+    }
+}
+
+/// Describes where a runtime error happened, innermost call first.
+pub fn backtrace(token: &Token, trace: &[Frame]) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = token.line;
+    for frame in trace {
+        lines.push(format!("[line {}] in {}", line, frame.name));
+        line = frame.line;
+    }
+    lines.push(format!("[line {}] in script", line));
+    lines
 }
 
 impl fmt::Display for Error {
@@ -15,6 +114,25 @@ impl fmt::Display for Error {
         match self {
             Self::DynError(e) => write!(f, "{}", e),
             Self::Return(r) => write!(f, "<return {}>", r),
+            Self::Break => write!(f, "<break>"),
+            Self::Continue => write!(f, "<continue>"),
+            Self::ParseError { message, .. } => write!(f, "{}", message),
+            Self::ResolveError { message, .. } => write!(f, "{}", message),
+            Self::RuntimeError {
+                token,
+                message,
+                trace,
+            } => write!(f, "{}\n{}", message, backtrace(token, trace).join("\n")),
+            Self::Throw {
+                token,
+                value,
+                trace,
+            } => write!(
+                f,
+                "Uncaught exception: {}\n{}",
+                value,
+                backtrace(token, trace).join("\n")
+            ),
         }
     }
 }
@@ -0,0 +1,69 @@
+use crate::error::{backtrace, Error};
+use crate::instance::InstanceGet;
+use crate::interpreter::Interpreter;
+use crate::list::List;
+use crate::object::Object;
+use crate::token::Token;
+use crate::Result;
+use std::fmt;
+use std::ptr;
+use std::rc::Rc;
+
+/// A runtime error caught by a `catch` clause.
+#[derive(Debug)]
+pub struct Exception {
+    message: String,
+    line: usize,
+    trace: Vec<String>,
+}
+
+impl Exception {
+    /// Turns an error into the value that a `catch` clause binds, or gives
+    /// the error back if it can't be caught.
+    pub fn catch(error: Error) -> std::result::Result<Rc<dyn Object>, Error> {
+        match error {
+            Error::Throw { value, .. } => Ok(value),
+            Error::RuntimeError {
+                token,
+                message,
+                trace,
+            } => Ok(Rc::new(Self {
+                message,
+                line: token.line,
+                trace: backtrace(&token, &trace),
+            })),
+            e => Err(e),
+        }
+    }
+}
+
+impl InstanceGet for Rc<Exception> {
+    fn get(&self, _interpreter: &mut Interpreter, name: &Token) -> Result<Rc<dyn Object>> {
+        match name.lexeme.as_str() {
+            "message" => Ok(Rc::new(self.message.clone())),
+            "line" => Ok(Rc::new(self.line as f64)),
+            "trace" => {
+                let lines = self.trace.iter().map(|l| Rc::new(l.clone()) as _);
+                Ok(Rc::new(List::new(lines.collect())))
+            }
+            _ => Err(Error::runtime(
+                name,
+                format!("Undefined property '{}'.", name.lexeme),
+            )),
+        }
+    }
+}
+
+impl Object for Exception {}
+
+impl PartialEq for Exception {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
@@ -1,5 +1,6 @@
 use crate::ast;
 use crate::object::Object;
+use crate::stmt::{Parameters, Stmt};
 use crate::token::Token;
 use crate::Result;
 use std::rc::Rc;
@@ -7,14 +8,30 @@ use std::rc::Rc;
 pub type VisitorResult = Result<Rc<dyn Object>>;
 
 ast!(Expr -> VisitorResult [
-    Assign(name: Token, value: Box<dyn Expr>),
+    // The operator is `=`, a compound one like `+=`, or `++`/`--` when postfix.
+    Assign(name: Token, operator: Token, value: Box<dyn Expr>),
     Binary(left: Box<dyn Expr>, operator: Token, right: Box<dyn Expr>),
-    Call(callee: Box<dyn Expr>, paren: Token, arguments: Vec<Box<dyn Expr>>),
+    // Named arguments always come after the positional ones.
+    Call(
+        callee: Box<dyn Expr>,
+        paren: Token,
+        arguments: Vec<Box<dyn Expr>>,
+        named: Vec<(Token, Box<dyn Expr>)>
+    ),
+    Comma(left: Box<dyn Expr>, right: Box<dyn Expr>),
+    Conditional(condition: Box<dyn Expr>, then_branch: Box<dyn Expr>, else_branch: Box<dyn Expr>),
     Get(object: Rc<dyn Expr>, name: Token),
     Grouping(expression: Box<dyn Expr>),
+    Index(object: Rc<dyn Expr>, bracket: Token, index: Rc<dyn Expr>),
+    Lambda(keyword: Token, params: Parameters, body: Vec<Rc<dyn Stmt>>),
+    List(elements: Vec<Box<dyn Expr>>),
     Literal(value: Rc<dyn Object>),
     Logical(left: Box<dyn Expr>, operator: Token, right: Box<dyn Expr>),
-    Set(object: Rc<dyn Expr>, name: Token, value: Box<dyn Expr>),
+    Map(brace: Token, entries: Vec<(Box<dyn Expr>, Box<dyn Expr>)>),
+    Set(object: Rc<dyn Expr>, name: Token, operator: Token, value: Box<dyn Expr>),
+    SetIndex(object: Rc<dyn Expr>, bracket: Token, index: Rc<dyn Expr>, operator: Token, value: Box<dyn Expr>),
+    // The token is the string part before the interpolated expression.
+    Stringify(token: Token, expression: Box<dyn Expr>),
     Super(keyword: Token, method: Token),
     This(keyword: Token),
     Unary(operator: Token, right: Box<dyn Expr>),
@@ -1,10 +1,14 @@
+use crate::callable::{Callable, LoxFunction};
 use crate::class::Class;
+use crate::error::Error;
+use crate::interpreter::Interpreter;
 use crate::object::Object;
 use crate::token::Token;
 use crate::Result;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
+use std::hash::Hasher;
 use std::ptr;
 use std::rc::Rc;
 
@@ -24,29 +28,78 @@ impl Instance {
 }
 
 pub trait InstanceGet {
-    fn get(&self, name: &Token) -> Result<Rc<dyn Object>>;
+    fn get(&self, interpreter: &mut Interpreter, name: &Token) -> Result<Rc<dyn Object>>;
 }
 
 impl InstanceGet for Rc<Instance> {
-    fn get(&self, name: &Token) -> Result<Rc<dyn Object>> {
+    fn get(&self, interpreter: &mut Interpreter, name: &Token) -> Result<Rc<dyn Object>> {
         if let Some(field) = self.fields.borrow().get(&name.lexeme) {
             return Ok(field.clone());
         }
+        if let Some(getter) = self.class.find_getter(&name.lexeme) {
+            return getter.bind(self.clone()).call(interpreter, name, &[]);
+        }
         if let Some(method) = self.class.find_method(&name.lexeme) {
             Ok(Rc::new(method.bind(self.clone())))
         } else {
-            Err(format!("Undefined property '{}'.", name.lexeme).into())
+            Err(Error::runtime(
+                name,
+                format!("Undefined property '{}'.", name.lexeme),
+            ))
         }
     }
 }
 
 impl Instance {
-    pub fn set(&self, name: &Token, value: Rc<dyn Object>) {
-        self.fields.borrow_mut().insert(name.lexeme.clone(), value);
+    /// Finds a method bound to this instance, skipping fields and getters.
+    pub fn find_method(self: &Rc<Self>, name: &str) -> Option<LoxFunction> {
+        self.class
+            .find_method(name)
+            .map(|method| method.bind(self.clone()))
+    }
+
+    /// Looks up a field or getter, but not a method, for destructuring.
+    pub fn get_field(
+        self: &Rc<Self>,
+        interpreter: &mut Interpreter,
+        name: &Token,
+    ) -> Result<Option<Rc<dyn Object>>> {
+        let has_field = self.fields.borrow().contains_key(&name.lexeme);
+        if has_field || self.class.find_getter(&name.lexeme).is_some() {
+            self.get(interpreter, name).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn is_instance_of(&self, class: &Class) -> bool {
+        self.class.is_subclass_of(class)
+    }
+
+    pub fn set(
+        self: &Rc<Self>,
+        interpreter: &mut Interpreter,
+        name: &Token,
+        value: Rc<dyn Object>,
+    ) -> Result<()> {
+        if let Some(setter) = self.class.find_setter(&name.lexeme) {
+            setter
+                .bind(self.clone())
+                .call(interpreter, name, &[value])?;
+        } else {
+            self.fields.borrow_mut().insert(name.lexeme.clone(), value);
+        }
+        Ok(())
     }
 }
 
-impl Object for Instance {}
+impl Object for Instance {
+    fn try_hash(&self, state: &mut dyn Hasher) -> Result<()> {
+        // Instances are only equal to themselves.
+        state.write_usize(self as *const Self as usize);
+        Ok(())
+    }
+}
 
 impl PartialEq for Instance {
     fn eq(&self, other: &Self) -> bool {
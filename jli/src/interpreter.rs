@@ -1,50 +1,105 @@
-use crate::callable::{Callable, LoxFunction, NativeFunction};
-use crate::class::Class as LoxClass;
+use crate::callable::{Callable, LoxFunction, NativeFunction, NativeMethod};
+use crate::class::{Class as LoxClass, Members};
 use crate::environment::Environment;
-use crate::error::Error;
+use crate::error::{Error, Frame};
+use crate::exception::Exception;
 use crate::expr::{
-    self, Assign, Binary, Call, Expr, Get, Grouping, Literal, Logical, Set, Super, This, Unary,
-    Variable,
+    self, Assign, Binary, Call, Comma, Conditional, Expr, Get, Grouping, Index, Lambda, List,
+    Literal, Logical, Map, Set, SetIndex, Stringify, Super, This, Unary, Variable,
 };
 use crate::instance::{Instance, InstanceGet};
-use crate::object::{Nil, Object};
-use crate::stmt::{self, Block, Class, Expression, Function, If, Print, Return, Stmt, Var, While};
+use crate::list::List as LoxList;
+use crate::lox;
+use crate::map::Map as LoxMap;
+use crate::module::Module;
+use crate::object::{Nil, Object, PrintGuard};
+use crate::resolver::Locals;
+use crate::stmt::{
+    self, Block, Break, Class, Const, Continue, Expression, ForIn, Function, If, Import, Match,
+    Pattern, Print, Return, Stmt, Throw, Try, Var, While,
+};
 use crate::token::Token;
 use crate::token::TokenType::*;
 use crate::Result;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::Hasher;
+use std::mem;
 use std::ops::Deref;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::slice;
+use std::vec;
+use std::{fs, iter};
 
 pub struct Interpreter {
-    globals: Rc<Environment>,
     environment: Rc<Environment>,
-    locals: HashMap<usize, usize>,
+    locals: Locals,
+    // Locals are keyed by the address of the nodes they were resolved from,
+    // so every program run keeps its statements alive for later REPL lines.
+    programs: Vec<Vec<Box<dyn Stmt>>>,
+    sources: Vec<Rc<str>>,
+    modules: HashMap<PathBuf, Rc<Module>>,
+    // The files being run or imported, the innermost last.
+    importing: Vec<PathBuf>,
+    search_path: Vec<PathBuf>,
 }
 
 impl Default for Interpreter {
     fn default() -> Self {
-        let globals: Rc<Environment> = Default::default();
-        globals.define("clock", Rc::new(NativeFunction::Clock));
         Interpreter {
-            globals: globals.clone(),
-            environment: globals,
+            environment: new_globals(),
             locals: HashMap::new(),
+            programs: vec![],
+            sources: vec![],
+            modules: HashMap::new(),
+            importing: vec![],
+            search_path: vec![],
         }
     }
 }
 
+/// Each file gets its own globals, starting with the native functions.
+fn new_globals() -> Rc<Environment> {
+    let globals: Rc<Environment> = Default::default();
+    globals
+        .define_constant("clock", Rc::new(NativeFunction::Clock))
+        .expect("New globals have no constants.");
+    globals
+}
+
 impl Interpreter {
-    pub fn interpret(&mut self, statements: &[Box<dyn Stmt>]) -> Result<()> {
-        for statement in statements {
-            self.execute(&**statement)?;
-        }
+    pub fn interpret(&mut self, statements: Vec<Box<dyn Stmt>>) -> Result<()> {
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.execute(&**statement));
+        self.programs.push(statements);
+        result
+    }
+
+    pub fn resolve(&mut self, locals: Locals) {
+        self.locals.extend(locals);
+    }
+
+    /// Keeps a copy of a program's source so that errors can show where in
+    /// it they happened. Returns the id that the program's spans refer to.
+    pub fn add_source(&mut self, source: &str) -> usize {
+        self.sources.push(source.into());
+        self.sources.len() - 1
+    }
+
+    pub fn source(&self, id: usize) -> &str {
+        &self.sources[id]
+    }
+
+    /// Sets the file that relative imports in the main program start from.
+    pub fn set_script_path(&mut self, path: &Path) -> Result<()> {
+        self.importing.push(path.canonicalize()?);
         Ok(())
     }
 
-    pub fn resolve(&mut self, expr: &dyn Expr, depth: usize) {
-        let expr_ptr = expr as *const dyn Expr as *const () as usize;
-        self.locals.insert(expr_ptr, depth);
+    pub fn set_search_path(&mut self, search_path: Vec<PathBuf>) {
+        self.search_path = search_path;
     }
 
     pub fn execute_block<S>(&mut self, statements: &[S], environment: Rc<Environment>) -> Result<()>
@@ -71,77 +126,491 @@ impl Interpreter {
         expr.accept(self)
     }
 
+    /// Tests `value` against `pattern`, defining the names it binds in
+    /// `environment`.
+    fn match_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: &Rc<dyn Object>,
+        environment: &Rc<Environment>,
+    ) -> Result<bool> {
+        match pattern {
+            Pattern::Wildcard => Ok(true),
+            Pattern::Binding(name) => {
+                environment.define(&name.lexeme, value.clone())?;
+                Ok(true)
+            }
+            Pattern::Literal(literal) => Ok(literal.equal(&**value)),
+            Pattern::Range(low, high) => {
+                let number = value.as_any().downcast_ref::<f64>();
+                Ok(number.is_some_and(|n| low <= n && n <= high))
+            }
+            Pattern::Class(variable, fields) => {
+                let class = self.evaluate_in(variable, environment.clone())?;
+                let Some(class) = class.as_any().downcast_ref::<LoxClass>() else {
+                    let message = format!("'{}' is not a class.", variable.name.lexeme);
+                    return Err(Error::runtime(&variable.name, message));
+                };
+                let Ok(instance) = value.clone().as_any_rc().downcast::<Instance>() else {
+                    return Ok(false);
+                };
+                if !instance.is_instance_of(class) {
+                    return Ok(false);
+                }
+                for (name, pattern) in fields {
+                    let Some(field) = instance.get_field(self, name)? else {
+                        return Ok(false);
+                    };
+                    if !self.match_pattern(pattern, &field, environment)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    pub fn evaluate_in(
+        &mut self,
+        expr: &dyn Expr,
+        environment: Rc<Environment>,
+    ) -> expr::VisitorResult {
+        let previous = mem::replace(&mut self.environment, environment);
+        let result = self.evaluate(expr);
+        self.environment = previous;
+        result
+    }
+
     fn execute(&mut self, stmt: &dyn Stmt) -> Result<()> {
         stmt.accept(self)
     }
 
+    fn call(
+        &mut self,
+        callee: Rc<dyn Object>,
+        paren: &Token,
+        arguments: &[Rc<dyn Object>],
+    ) -> expr::VisitorResult {
+        self.call_named(callee, paren, arguments, &[])
+    }
+
+    fn call_named(
+        &mut self,
+        callee: Rc<dyn Object>,
+        paren: &Token,
+        arguments: &[Rc<dyn Object>],
+        named: &[(Token, Rc<dyn Object>)],
+    ) -> expr::VisitorResult {
+        let callee_any = callee.as_any();
+        // Sadly, downcast_ref::<dyn Callable> doesn't work.
+        if let Some(function) = callee_any.downcast_ref::<LoxFunction>() {
+            self.call_function(function, paren, arguments, named)
+        } else if let Some(function) = callee_any.downcast_ref::<NativeFunction>() {
+            self.call_function(function, paren, arguments, named)
+        } else if let Some(function) = callee_any.downcast_ref::<NativeMethod>() {
+            self.call_function(function, paren, arguments, named)
+        } else if let Ok(function) = Rc::downcast::<LoxClass>(callee.as_any_rc()) {
+            self.call_function(&function, paren, arguments, named)
+        } else {
+            Err(Error::runtime(
+                paren,
+                "Can only call functions and classes.",
+            ))
+        }
+    }
+
     fn call_function<F>(
         &mut self,
         function: &F,
+        paren: &Token,
         arguments: &[Rc<dyn Object>],
+        named: &[(Token, Rc<dyn Object>)],
     ) -> expr::VisitorResult
     where
         F: Callable,
     {
-        if arguments.len() != function.arity() {
-            let message = format!(
-                "Expected {} arguments but got {}.",
-                function.arity(),
-                arguments.len()
-            );
-            return Err(message.into());
+        let arity = function.arity();
+        let count = arguments.len() + named.len();
+        if !arity.accepts(count) {
+            let message = format!("Expected {} but got {}.", arity, count);
+            return Err(Error::runtime(paren, message));
+        }
+        function
+            .call_named(self, paren, arguments, named)
+            .map_err(|e| e.at(paren))
+    }
+
+    fn get_property(&mut self, object: Rc<dyn Object>, name: &Token) -> expr::VisitorResult {
+        let object = object.as_any_rc();
+        let object = match object.downcast::<Instance>() {
+            Ok(instance) => return instance.get(self, name),
+            Err(object) => object,
+        };
+        let object = match object.downcast::<LoxClass>() {
+            Ok(class) => return class.get(self, name),
+            Err(object) => object,
+        };
+        let object = match object.downcast::<LoxList>() {
+            Ok(list) => return list.get(self, name),
+            Err(object) => object,
+        };
+        let object = match object.downcast::<LoxMap>() {
+            Ok(map) => return map.get(self, name),
+            Err(object) => object,
+        };
+        let object = match object.downcast::<Module>() {
+            Ok(module) => return module.get(self, name),
+            Err(object) => object,
+        };
+        match object.downcast::<Exception>() {
+            Ok(exception) => exception.get(self, name),
+            Err(_) => Err(Error::runtime(name, "Only instances have properties.")),
+        }
+    }
+
+    fn iterate(&mut self, iterable: Rc<dyn Object>, keyword: &Token) -> Result<Iteration> {
+        let iterable_any = iterable.as_any();
+        let elements = if let Some(list) = iterable_any.downcast_ref::<LoxList>() {
+            list.elements()
+        } else if let Some(map) = iterable_any.downcast_ref::<LoxMap>() {
+            map.keys().elements()
+        } else if let Some(string) = iterable_any.downcast_ref::<String>() {
+            string
+                .chars()
+                .map(|c| Rc::new(c.to_string()) as Rc<dyn Object>)
+                .collect()
+        } else if iterable_any.is::<Instance>() {
+            let method = self.get_property(iterable, &identifier("iterator", keyword))?;
+            let iterator = self.call(method, keyword, &[])?;
+            return Ok(Iteration::Protocol(iterator));
+        } else {
+            return Err(Error::runtime(
+                keyword,
+                "Can only iterate over lists, maps, strings and instances.",
+            ));
+        };
+        Ok(Iteration::Native(elements.into_iter()))
+    }
+
+    fn next(
+        &mut self,
+        iteration: &mut Iteration,
+        keyword: &Token,
+    ) -> Result<Option<Rc<dyn Object>>> {
+        match iteration {
+            Iteration::Native(elements) => Ok(elements.next()),
+            Iteration::Protocol(iterator) => {
+                let has_next =
+                    self.get_property(iterator.clone(), &identifier("hasNext", keyword))?;
+                if !self.call(has_next, keyword, &[])?.truthy() {
+                    return Ok(None);
+                }
+                let next = self.get_property(iterator.clone(), &identifier("next", keyword))?;
+                self.call(next, keyword, &[]).map(Some)
+            }
+        }
+    }
+
+    /// Calls the special method `name` when `object` is an instance whose
+    /// class defines it, so that classes can hook into operators.
+    fn call_special(
+        &mut self,
+        object: &Rc<dyn Object>,
+        name: &str,
+        token: &Token,
+        arguments: &[Rc<dyn Object>],
+    ) -> Result<Option<Rc<dyn Object>>> {
+        let Ok(instance) = object.clone().as_any_rc().downcast::<Instance>() else {
+            return Ok(None);
+        };
+        match instance.find_method(name) {
+            Some(method) => self.call_function(&method, token, arguments, &[]).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Compares two values the way `==` does, calling an `equals` method.
+    pub fn equal(
+        &mut self,
+        left: &Rc<dyn Object>,
+        right: &Rc<dyn Object>,
+        token: &Token,
+    ) -> Result<bool> {
+        match self.call_special(left, "equals", token, slice::from_ref(right))? {
+            Some(equal) => Ok(equal.truthy()),
+            None => Ok(left.equal(&**right)),
+        }
+    }
+
+    /// Hashes a map key consistently with `equal`, calling a `hash` method
+    /// and hashing the number or string it returns.
+    pub fn hash(&mut self, value: &Rc<dyn Object>, token: &Token) -> Result<u64> {
+        let mut hasher = DefaultHasher::new();
+        if let Some(hash) = self.call_special(value, "hash", token, &[])? {
+            let hash_any = hash.as_any();
+            if !hash_any.is::<f64>() && !hash_any.is::<String>() {
+                let message = "hash() must return a number or a string.";
+                return Err(Error::runtime(token, message));
+            }
+            hash.try_hash(&mut hasher).map_err(|e| e.at(token))?;
+            return Ok(hasher.finish());
+        }
+        let instance = value.clone().as_any_rc().downcast::<Instance>();
+        if instance.is_ok_and(|instance| instance.find_method("equals").is_some()) {
+            let message = "An instance with an equals() method needs hash() to be a map key.";
+            return Err(Error::runtime(token, message));
+        }
+        value.try_hash(&mut hasher).map_err(|e| e.at(token))?;
+        Ok(hasher.finish())
+    }
+
+    fn binary(
+        &mut self,
+        left: Rc<dyn Object>,
+        operator: &Token,
+        right: Rc<dyn Object>,
+    ) -> expr::VisitorResult {
+        let special = match operator.token_type {
+            BangEqual | EqualEqual => "equals",
+            Greater => "gt",
+            GreaterEqual => "ge",
+            Less => "lt",
+            LessEqual => "le",
+            Minus => "sub",
+            Percent => "mod",
+            Plus => "add",
+            Slash => "div",
+            Star => "mul",
+            StarStar => "pow",
+            TildeSlash => "intDiv",
+            _ => unreachable!(),
+        };
+        match self.call_special(&left, special, operator, slice::from_ref(&right))? {
+            Some(equal) if operator.token_type == BangEqual => Ok(Rc::new(!equal.truthy())),
+            Some(result) => Ok(result),
+            None => binary(&*left, operator, &*right),
+        }
+    }
+
+    /// Combines a target's current value with the operand of a compound
+    /// assignment, returning the value to store and the value of the expression.
+    fn compound_assignment(
+        &mut self,
+        operator: &Token,
+        current: Rc<dyn Object>,
+        operand: Rc<dyn Object>,
+    ) -> Result<(Rc<dyn Object>, Rc<dyn Object>)> {
+        let token_type = match operator.token_type {
+            PlusEqual | PlusPlus => Plus,
+            MinusEqual | MinusMinus => Minus,
+            StarEqual => Star,
+            SlashEqual => Slash,
+            PercentEqual => Percent,
+            _ => unreachable!(),
+        };
+        let binary_operator = Token {
+            token_type,
+            ..operator.clone()
+        };
+        let value = self.binary(current.clone(), &binary_operator, operand)?;
+        match operator.token_type {
+            // Postfix operators evaluate to the value from before the update.
+            PlusPlus | MinusMinus => Ok((value, current)),
+            _ => Ok((value.clone(), value)),
+        }
+    }
+
+    fn get_index(
+        &mut self,
+        object: Rc<dyn Object>,
+        index: Rc<dyn Object>,
+        bracket: &Token,
+    ) -> expr::VisitorResult {
+        if let Some(value) =
+            self.call_special(&object, "index", bracket, slice::from_ref(&index))?
+        {
+            return Ok(value);
+        }
+        let object = object.as_any();
+        let value = if let Some(list) = object.downcast_ref::<LoxList>() {
+            list.get_index(&*index)
+        } else if let Some(map) = object.downcast_ref::<LoxMap>() {
+            map.get_index(self, bracket, index)
+        } else {
+            return Err(Error::runtime(
+                bracket,
+                "Only lists and maps can be indexed.",
+            ));
+        };
+        value.map_err(|e| e.at(bracket))
+    }
+
+    fn set_index(
+        &mut self,
+        object: Rc<dyn Object>,
+        index: Rc<dyn Object>,
+        value: Rc<dyn Object>,
+        bracket: &Token,
+    ) -> Result<()> {
+        let arguments = [index.clone(), value.clone()];
+        if self
+            .call_special(&object, "setIndex", bracket, &arguments)?
+            .is_some()
+        {
+            return Ok(());
+        }
+        let object = object.as_any();
+        let result = if let Some(list) = object.downcast_ref::<LoxList>() {
+            list.set_index(&*index, value)
+        } else if let Some(map) = object.downcast_ref::<LoxMap>() {
+            map.set_index(self, bracket, index, value)
+        } else {
+            return Err(Error::runtime(
+                bracket,
+                "Only lists and maps can be indexed.",
+            ));
+        };
+        result.map_err(|e| e.at(bracket))
+    }
+
+    pub fn stringify(&mut self, object: Rc<dyn Object>, token: &Token) -> Result<String> {
+        if let Some(string) = self.call_special(&object, "toString", token, &[])? {
+            return Ok(format!("{}", string));
+        }
+        // Elements of collections may have a `toString()` of their own.
+        let object_any = object.as_any();
+        if let Some(list) = object_any.downcast_ref::<LoxList>() {
+            let Some(_guard) = PrintGuard::enter(list) else {
+                return Ok("[...]".to_string());
+            };
+            let mut elements = vec![];
+            for element in list.elements() {
+                elements.push(self.stringify(element, token)?);
+            }
+            Ok(format!("[{}]", elements.join(", ")))
+        } else if let Some(map) = object_any.downcast_ref::<LoxMap>() {
+            let Some(_guard) = PrintGuard::enter(map) else {
+                return Ok("{...}".to_string());
+            };
+            let mut entries = vec![];
+            for (key, value) in map.entries() {
+                let key = self.stringify(key, token)?;
+                entries.push(format!("{}: {}", key, self.stringify(value, token)?));
+            }
+            Ok(format!("{{{}}}", entries.join(", ")))
+        } else {
+            Ok(format!("{}", object))
         }
-        function.call(self, arguments)
     }
 
     fn look_up_variable(&self, name: &Token, expr: &dyn Expr) -> expr::VisitorResult {
         let expr_ptr = expr as *const dyn Expr as *const () as usize;
         if let Some(distance) = self.locals.get(&expr_ptr) {
-            self.environment.get_at(*distance, &name.lexeme)
+            self.environment
+                .get_at(*distance, &name.lexeme)
+                .map_err(|e| e.at(name))
         } else {
-            self.globals.get(name)
+            self.environment.globals().get(name)
+        }
+    }
+
+    /// Runs the module at `path` the first time it's imported, and returns
+    /// the cached module afterwards.
+    fn import(&mut self, path: &Token) -> Result<Rc<Module>> {
+        let LoxString(relative_path) = &path.token_type else {
+            unreachable!("The parser only accepts strings as module paths.")
+        };
+        let Some(full_path) = self.find_module(relative_path) else {
+            let message = format!("Can't find module '{}'.", relative_path);
+            return Err(Error::runtime(path, message));
+        };
+        if let Some(module) = self.modules.get(&full_path) {
+            return Ok(module.clone());
         }
+        if let Some(i) = self.importing.iter().position(|p| *p == full_path) {
+            let cycle: Vec<_> = self.importing[i..]
+                .iter()
+                .chain([&full_path])
+                .map(|p| p.file_name().unwrap_or_default().to_string_lossy())
+                .collect();
+            let message = format!("Import cycle: {}.", cycle.join(" -> "));
+            return Err(Error::runtime(path, message));
+        }
+        let source = fs::read_to_string(&full_path).map_err(|e| {
+            Error::runtime(
+                path,
+                format!("Can't read module '{}': {}.", relative_path, e),
+            )
+        })?;
+        let source_id = self.add_source(&source);
+        let Some((statements, locals)) = lox::compile(&source, source_id, Some(relative_path))
+        else {
+            let message = format!("Can't compile module '{}'.", relative_path);
+            return Err(Error::runtime(path, message));
+        };
+        // The module needs its locals while it runs, but they are keyed by
+        // the addresses of its nodes, so they must go if the nodes are dropped.
+        let keys: Vec<usize> = locals.keys().copied().collect();
+        self.resolve(locals);
+        let globals = new_globals();
+        self.importing.push(full_path.clone());
+        let result = self.execute_block(&statements, globals.clone());
+        self.importing.pop();
+        if let Err(mut e) = result {
+            for key in keys {
+                self.locals.remove(&key);
+            }
+            e.push_frame(Frame::module(relative_path, path.line));
+            return Err(e);
+        }
+        let name = full_path.file_stem().unwrap_or_default().to_string_lossy();
+        let module = Rc::new(Module::new(name.into_owned(), globals, statements));
+        self.modules.insert(full_path, module.clone());
+        Ok(module)
+    }
+
+    /// Looks for a module next to the importing file, then in the search path.
+    fn find_module(&self, path: &str) -> Option<PathBuf> {
+        let base = match self.importing.last().and_then(|p| p.parent()) {
+            Some(directory) => directory.to_path_buf(),
+            None => PathBuf::new(),
+        };
+        iter::once(&base)
+            .chain(&self.search_path)
+            .map(|directory| directory.join(path))
+            .find(|p| p.is_file())
+            .and_then(|p| p.canonicalize().ok())
     }
 }
 
 impl expr::Visitor<expr::VisitorResult> for Interpreter {
     fn visit_assign_expr(&mut self, expr: &Assign) -> expr::VisitorResult {
-        let value = self.evaluate(&*expr.value)?;
+        let (value, result) = if expr.operator.token_type == Equal {
+            let value = self.evaluate(&*expr.value)?;
+            (value.clone(), value)
+        } else {
+            let current = self.look_up_variable(&expr.name, expr)?;
+            let operand = self.evaluate(&*expr.value)?;
+            self.compound_assignment(&expr.operator, current, operand)?
+        };
         let expr_ptr = expr as *const dyn Expr as *const () as usize;
         if let Some(distance) = self.locals.get(&expr_ptr) {
-            self.environment
-                .assign_at(*distance, &expr.name, value.clone());
+            self.environment.assign_at(*distance, &expr.name, value);
         } else {
-            self.globals.assign(&expr.name, value.clone())?;
+            self.environment.globals().assign(&expr.name, value)?;
         }
-        Ok(value)
+        Ok(result)
     }
 
     fn visit_get_expr(&mut self, expr: &Get) -> expr::VisitorResult {
         let object = self.evaluate(&*expr.object)?;
-        if let Ok(instance) = Rc::downcast::<Instance>(object.as_any_rc()) {
-            instance.get(&expr.name)
-        } else {
-            Err("Only instances have properties.".into())
-        }
+        self.get_property(object, &expr.name)
     }
 
     fn visit_binary_expr(&mut self, expr: &Binary) -> expr::VisitorResult {
-        let left = &*self.evaluate(&*expr.left)?;
-        let right = &*self.evaluate(&*expr.right)?;
-        Ok(match expr.operator.token_type {
-            BangEqual => Rc::new(!left.equal(right)),
-            EqualEqual => Rc::new(left.equal(right)),
-            Greater => Rc::new(left.try_gt(right)?),
-            GreaterEqual => Rc::new(left.try_ge(right)?),
-            Less => Rc::new(left.try_lt(right)?),
-            LessEqual => Rc::new(left.try_le(right)?),
-            Minus => Rc::new(left.try_sub(right)?),
-            Plus => left.try_add(right)?,
-            Slash => Rc::new(left.try_div(right)?),
-            Star => Rc::new(left.try_mul(right)?),
-            _ => unreachable!(),
-        })
+        let left = self.evaluate(&*expr.left)?;
+        let right = self.evaluate(&*expr.right)?;
+        self.binary(left, &expr.operator, right)
     }
 
     fn visit_call_expr(&mut self, expr: &Call) -> expr::VisitorResult {
@@ -150,16 +619,23 @@ impl expr::Visitor<expr::VisitorResult> for Interpreter {
         for argument in &expr.arguments {
             arguments.push(self.evaluate(&**argument)?);
         }
-        let callee_any = callee.as_any();
-        // Sadly, downcast_ref::<dyn Callable> doesn't work.
-        if let Some(function) = callee_any.downcast_ref::<LoxFunction>() {
-            self.call_function(function, &arguments)
-        } else if let Some(function) = callee_any.downcast_ref::<NativeFunction>() {
-            self.call_function(function, &arguments)
-        } else if let Ok(function) = Rc::downcast::<LoxClass>(callee.as_any_rc()) {
-            self.call_function(&function, &arguments)
+        let mut named = vec![];
+        for (name, argument) in &expr.named {
+            named.push((name.clone(), self.evaluate(&**argument)?));
+        }
+        self.call_named(callee, &expr.paren, &arguments, &named)
+    }
+
+    fn visit_comma_expr(&mut self, expr: &Comma) -> expr::VisitorResult {
+        self.evaluate(&*expr.left)?;
+        self.evaluate(&*expr.right)
+    }
+
+    fn visit_conditional_expr(&mut self, expr: &Conditional) -> expr::VisitorResult {
+        if self.evaluate(&*expr.condition)?.truthy() {
+            self.evaluate(&*expr.then_branch)
         } else {
-            Err("Can only call functions and classes.".into())
+            self.evaluate(&*expr.else_branch)
         }
     }
 
@@ -167,10 +643,44 @@ impl expr::Visitor<expr::VisitorResult> for Interpreter {
         self.evaluate(&*expr.expression)
     }
 
+    fn visit_index_expr(&mut self, expr: &Index) -> expr::VisitorResult {
+        let object = self.evaluate(&*expr.object)?;
+        let index = self.evaluate(&*expr.index)?;
+        self.get_index(object, index, &expr.bracket)
+    }
+
+    fn visit_lambda_expr(&mut self, expr: &Lambda) -> expr::VisitorResult {
+        let declaration =
+            Function::new(expr.keyword.clone(), expr.params.clone(), expr.body.clone());
+        Ok(Rc::new(LoxFunction::anonymous(
+            Rc::new(declaration),
+            self.environment.clone(),
+        )))
+    }
+
+    fn visit_list_expr(&mut self, expr: &List) -> expr::VisitorResult {
+        let mut elements = vec![];
+        for element in &expr.elements {
+            elements.push(self.evaluate(&**element)?);
+        }
+        Ok(Rc::new(LoxList::new(elements)))
+    }
+
     fn visit_literal_expr(&mut self, expr: &Literal) -> expr::VisitorResult {
         Ok(expr.value.clone())
     }
 
+    fn visit_map_expr(&mut self, expr: &Map) -> expr::VisitorResult {
+        let map = LoxMap::default();
+        for (key, value) in &expr.entries {
+            let key = self.evaluate(&**key)?;
+            let value = self.evaluate(&**value)?;
+            map.set_index(self, &expr.brace, key, value)
+                .map_err(|e| e.at(&expr.brace))?;
+        }
+        Ok(Rc::new(map))
+    }
+
     fn visit_logical_expr(&mut self, expr: &Logical) -> expr::VisitorResult {
         let left = self.evaluate(&*expr.left)?;
         if expr.operator.token_type == Or {
@@ -185,32 +695,68 @@ impl expr::Visitor<expr::VisitorResult> for Interpreter {
 
     fn visit_set_expr(&mut self, expr: &Set) -> expr::VisitorResult {
         let object = self.evaluate(&*expr.object)?;
-        if let Some(instance) = (*object).as_any().downcast_ref::<Instance>() {
-            let value = self.evaluate(&*expr.value)?;
-            instance.set(&expr.name, value.clone());
-            Ok(value)
+        if let Ok(instance) = Rc::downcast::<Instance>(object.as_any_rc()) {
+            let (value, result) = if expr.operator.token_type == Equal {
+                let value = self.evaluate(&*expr.value)?;
+                (value.clone(), value)
+            } else {
+                let current = instance.get(self, &expr.name)?;
+                let operand = self.evaluate(&*expr.value)?;
+                self.compound_assignment(&expr.operator, current, operand)?
+            };
+            instance.set(self, &expr.name, value)?;
+            Ok(result)
         } else {
-            Err("Only instances have fields.".into())
+            Err(Error::runtime(&expr.name, "Only instances have fields."))
         }
     }
 
+    fn visit_setindex_expr(&mut self, expr: &SetIndex) -> expr::VisitorResult {
+        let object = self.evaluate(&*expr.object)?;
+        let index = self.evaluate(&*expr.index)?;
+        let (value, result) = if expr.operator.token_type == Equal {
+            let value = self.evaluate(&*expr.value)?;
+            (value.clone(), value)
+        } else {
+            let current = self.get_index(object.clone(), index.clone(), &expr.bracket)?;
+            let operand = self.evaluate(&*expr.value)?;
+            self.compound_assignment(&expr.operator, current, operand)?
+        };
+        self.set_index(object, index, value, &expr.bracket)?;
+        Ok(result)
+    }
+
+    fn visit_stringify_expr(&mut self, expr: &Stringify) -> expr::VisitorResult {
+        let value = self.evaluate(&*expr.expression)?;
+        Ok(Rc::new(self.stringify(value, &expr.token)?))
+    }
+
     fn visit_super_expr(&mut self, expr: &Super) -> expr::VisitorResult {
         let expr_ptr = expr as *const dyn Expr as *const () as usize;
         // ugh.
         if let Some(distance) = self.locals.get(&expr_ptr) {
-            let var = self.environment.get_at(*distance, "super")?;
+            let var = self
+                .environment
+                .get_at(*distance, "super")
+                .map_err(|e| e.at(&expr.keyword))?;
             if let Ok(superclass) = Rc::downcast::<LoxClass>(var.as_any_rc()) {
-                let var = self.environment.get_at(*distance - 1, "this")?;
+                let var = self
+                    .environment
+                    .get_at(*distance - 1, "this")
+                    .map_err(|e| e.at(&expr.keyword))?;
                 if let Ok(object) = Rc::downcast::<Instance>(var.as_any_rc()) {
-                    if let Some(method) = superclass.find_method(&expr.method.lexeme) {
+                    if let Some(getter) = superclass.find_getter(&expr.method.lexeme) {
+                        return getter.bind(object).call(self, &expr.method, &[]);
+                    } else if let Some(method) = superclass.find_method(&expr.method.lexeme) {
                         return Ok(Rc::new(method.bind(object)));
                     } else {
-                        return Err(format!("Undefined property '{}'.", expr.method.lexeme).into());
+                        let message = format!("Undefined property '{}'.", expr.method.lexeme);
+                        return Err(Error::runtime(&expr.method, message));
                     }
                 }
             }
         }
-        Err("super not found in locals.".into())
+        Err(Error::runtime(&expr.keyword, "super not found in locals."))
     }
 
     fn visit_this_expr(&mut self, expr: &This) -> expr::VisitorResult {
@@ -218,10 +764,15 @@ impl expr::Visitor<expr::VisitorResult> for Interpreter {
     }
 
     fn visit_unary_expr(&mut self, expr: &Unary) -> expr::VisitorResult {
-        let right = &*self.evaluate(&*expr.right)?;
+        let right = self.evaluate(&*expr.right)?;
+        if expr.operator.token_type == Minus {
+            if let Some(result) = self.call_special(&right, "neg", &expr.operator, &[])? {
+                return Ok(result);
+            }
+        }
         Ok(match expr.operator.token_type {
             Bang => Rc::new(!right.truthy()),
-            Minus => Rc::new(right.try_neg()?),
+            Minus => Rc::new(right.try_neg().map_err(|e| e.at(&expr.operator))?),
             _ => unreachable!(),
         })
     }
@@ -240,32 +791,48 @@ impl stmt::Visitor<stmt::VisitorResult> for Interpreter {
         Ok(())
     }
 
+    fn visit_break_stmt(&mut self, _stmt: &Break) -> stmt::VisitorResult {
+        Err(Error::Break)
+    }
+
     fn visit_class_stmt(&mut self, stmt: &Class) -> stmt::VisitorResult {
         let superclass = if let Some(var) = &stmt.superclass {
             let evaluated = self.evaluate(var)?;
             if let Ok(sc) = Rc::downcast::<LoxClass>(evaluated.as_any_rc()) {
                 Some(sc)
             } else {
-                return Err(format!("Superclass ({}) must be a class.", var.name.lexeme).into());
+                let message = format!("Superclass ({}) must be a class.", var.name.lexeme);
+                return Err(Error::runtime(&var.name, message));
             }
         } else {
             None
         };
-        self.environment.define(&stmt.name.lexeme, Rc::new(Nil));
+        self.environment
+            .define(&stmt.name.lexeme, Rc::new(Nil))
+            .map_err(|e| e.at(&stmt.name))?;
         if let Some(sc) = &superclass {
             self.environment = Rc::new(Environment::new_with_enclosing(self.environment.clone()));
-            self.environment.define("super", sc.clone());
-        }
-        let mut methods = HashMap::new();
-        for method in &stmt.methods {
-            let function = LoxFunction::new(
-                method.clone(),
-                self.environment.clone(),
-                method.name.lexeme == "init",
-            );
-            methods.insert(method.name.lexeme.clone(), Rc::new(function));
-        }
-        let class = LoxClass::new(stmt.name.lexeme.clone(), superclass.clone(), methods);
+            self.environment.define("super", sc.clone())?;
+        }
+        // Only instance methods can be initializers.
+        let members = |functions: &[Rc<Function>], are_methods: bool| -> Members {
+            let mut members = HashMap::new();
+            for function in functions {
+                let is_initializer = are_methods && function.name.lexeme == "init";
+                let member =
+                    LoxFunction::new(function.clone(), self.environment.clone(), is_initializer);
+                members.insert(function.name.lexeme.clone(), Rc::new(member));
+            }
+            members
+        };
+        let class = LoxClass::new(
+            stmt.name.lexeme.clone(),
+            superclass.clone(),
+            members(&stmt.methods, true),
+            members(&stmt.class_methods, false),
+            members(&stmt.getters, false),
+            members(&stmt.setters, false),
+        );
         if superclass.is_some() {
             self.environment = self.environment.enclosing.clone().expect("No ancestor");
         }
@@ -273,11 +840,38 @@ impl stmt::Visitor<stmt::VisitorResult> for Interpreter {
         Ok(())
     }
 
+    fn visit_const_stmt(&mut self, stmt: &Const) -> stmt::VisitorResult {
+        let value = self.evaluate(&*stmt.initializer)?;
+        self.environment
+            .define_constant(&stmt.name.lexeme, value)
+            .map_err(|e| e.at(&stmt.name))
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &Continue) -> stmt::VisitorResult {
+        Err(Error::Continue)
+    }
+
     fn visit_expression_stmt(&mut self, stmt: &Expression) -> stmt::VisitorResult {
         self.evaluate(&*stmt.expression)?;
         Ok(())
     }
 
+    fn visit_forin_stmt(&mut self, stmt: &ForIn) -> stmt::VisitorResult {
+        let iterable = self.evaluate(&*stmt.iterable)?;
+        let mut iteration = self.iterate(iterable, &stmt.keyword)?;
+        while let Some(value) = self.next(&mut iteration, &stmt.keyword)? {
+            // Each element gets its own variable, so closures don't share it.
+            let environment = Environment::new_with_enclosing(self.environment.clone());
+            environment.define(&stmt.name.lexeme, value)?;
+            match self.execute_block(&[&*stmt.body], Rc::new(environment)) {
+                Ok(()) | Err(Error::Continue) => (),
+                Err(Error::Break) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
     fn visit_function_stmt(&mut self, stmt: &Function) -> stmt::VisitorResult {
         // Poor man's Clone.
         let declaration = Function::new(stmt.name.clone(), stmt.params.clone(), stmt.body.clone());
@@ -286,8 +880,9 @@ impl stmt::Visitor<stmt::VisitorResult> for Interpreter {
             self.environment.clone(),
             false,
         ));
-        self.environment.define(&stmt.name.lexeme, function);
-        Ok(())
+        self.environment
+            .define(&stmt.name.lexeme, function)
+            .map_err(|e| e.at(&stmt.name))
     }
 
     fn visit_if_stmt(&mut self, stmt: &If) -> stmt::VisitorResult {
@@ -300,9 +895,30 @@ impl stmt::Visitor<stmt::VisitorResult> for Interpreter {
         Ok(())
     }
 
+    fn visit_match_stmt(&mut self, stmt: &Match) -> stmt::VisitorResult {
+        let subject = self.evaluate(&*stmt.subject)?;
+        for case in &stmt.cases {
+            let environment = Rc::new(Environment::new_with_enclosing(self.environment.clone()));
+            let mut matched = false;
+            for pattern in &case.patterns {
+                if self.match_pattern(pattern, &subject, &environment)? {
+                    matched = true;
+                    break;
+                }
+            }
+            if let (true, Some(guard)) = (matched, &case.guard) {
+                matched = self.evaluate_in(&**guard, environment.clone())?.truthy();
+            }
+            if matched {
+                return self.execute_block(slice::from_ref(&case.body), environment);
+            }
+        }
+        Ok(())
+    }
+
     fn visit_print_stmt(&mut self, stmt: &Print) -> stmt::VisitorResult {
         let value = self.evaluate(&*stmt.expression)?;
-        println!("{}", stringify(&*value));
+        println!("{}", self.stringify(value, &stmt.keyword)?);
         Ok(())
     }
 
@@ -314,25 +930,112 @@ impl stmt::Visitor<stmt::VisitorResult> for Interpreter {
         Err(Error::Return(value))
     }
 
+    fn visit_throw_stmt(&mut self, stmt: &Throw) -> stmt::VisitorResult {
+        let value = self.evaluate(&*stmt.value)?;
+        Err(Error::Throw {
+            token: Box::new(stmt.keyword.clone()),
+            value,
+            trace: vec![],
+        })
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &Try) -> stmt::VisitorResult {
+        let environment = Environment::new_with_enclosing(self.environment.clone());
+        let mut result = self.execute_block(&stmt.body, Rc::new(environment));
+        if let Some((name, body)) = &stmt.catch {
+            result = match result.map_err(Exception::catch) {
+                Err(Ok(value)) => {
+                    let environment = Environment::new_with_enclosing(self.environment.clone());
+                    environment.define(&name.lexeme, value)?;
+                    self.execute_block(body, Rc::new(environment))
+                }
+                Err(Err(e)) => Err(e),
+                Ok(()) => Ok(()),
+            };
+        }
+        // Runs even when the try or catch block returns, breaks or throws.
+        if let Some(body) = &stmt.finally {
+            let environment = Environment::new_with_enclosing(self.environment.clone());
+            self.execute_block(body, Rc::new(environment))?;
+        }
+        result
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &Import) -> stmt::VisitorResult {
+        let module = self.import(&stmt.path)?;
+        if let Some(alias) = &stmt.alias {
+            self.environment
+                .define(&alias.lexeme, module.clone())
+                .map_err(|e| e.at(alias))?;
+        }
+        for name in &stmt.names {
+            let value = module.get(self, name)?;
+            self.environment
+                .define(&name.lexeme, value)
+                .map_err(|e| e.at(name))?;
+        }
+        Ok(())
+    }
+
     fn visit_var_stmt(&mut self, stmt: &Var) -> stmt::VisitorResult {
         let value = match &stmt.initializer {
             Some(initializer) => self.evaluate(&**initializer)?,
             None => Rc::new(Nil),
         };
-        self.environment.define(&stmt.name.lexeme, value);
-        Ok(())
+        self.environment
+            .define(&stmt.name.lexeme, value)
+            .map_err(|e| e.at(&stmt.name))
     }
 
     fn visit_while_stmt(&mut self, stmt: &While) -> stmt::VisitorResult {
-        let mut value = self.evaluate(&*stmt.condition)?;
-        while value.truthy() {
-            self.execute(&*stmt.body)?;
-            value = self.evaluate(&*stmt.condition)?;
+        while self.evaluate(&*stmt.condition)?.truthy() {
+            match self.execute(&*stmt.body) {
+                Ok(()) | Err(Error::Continue) => (),
+                Err(Error::Break) => break,
+                Err(e) => return Err(e),
+            }
+            if let Some(increment) = &stmt.increment {
+                self.evaluate(&**increment)?;
+            }
         }
         Ok(())
     }
 }
 
-fn stringify(object: &dyn Object) -> String {
-    format!("{}", object)
+/// The state of a for-in loop.
+enum Iteration {
+    /// Built-in collections are copied when the loop starts.
+    Native(vec::IntoIter<Rc<dyn Object>>),
+    /// Instances hand out an iterator with `hasNext()` and `next()` methods.
+    Protocol(Rc<dyn Object>),
+}
+
+fn binary(left: &dyn Object, operator: &Token, right: &dyn Object) -> expr::VisitorResult {
+    let result: expr::VisitorResult = match operator.token_type {
+        BangEqual => Ok(Rc::new(!left.equal(right))),
+        EqualEqual => Ok(Rc::new(left.equal(right))),
+        Greater => left.try_gt(right).map(|v| Rc::new(v) as _),
+        GreaterEqual => left.try_ge(right).map(|v| Rc::new(v) as _),
+        Less => left.try_lt(right).map(|v| Rc::new(v) as _),
+        LessEqual => left.try_le(right).map(|v| Rc::new(v) as _),
+        Minus => left.try_sub(right).map(|v| Rc::new(v) as _),
+        Percent => left.try_rem(right).map(|v| Rc::new(v) as _),
+        Plus => left.try_add(right),
+        Slash => left.try_div(right).map(|v| Rc::new(v) as _),
+        Star => left.try_mul(right).map(|v| Rc::new(v) as _),
+        StarStar => left.try_pow(right).map(|v| Rc::new(v) as _),
+        TildeSlash => left.try_int_div(right).map(|v| Rc::new(v) as _),
+        _ => unreachable!(),
+    };
+    result.map_err(|e| e.at(operator))
+}
+
+/// Makes a token for a method that the interpreter calls on its own.
+fn identifier(name: &str, at: &Token) -> Token {
+    Token::new(
+        Identifier(name.to_string()),
+        name.to_string(),
+        at.line,
+        at.span,
+    )
 }
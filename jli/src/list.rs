@@ -0,0 +1,161 @@
+use crate::callable::NativeMethod;
+use crate::error::Error;
+use crate::instance::InstanceGet;
+use crate::interpreter::Interpreter;
+use crate::object::{Nil, Object, PrintGuard};
+use crate::token::Token;
+use crate::Result;
+use std::cell::RefCell;
+use std::fmt;
+use std::ptr;
+use std::rc::Rc;
+
+#[derive(Debug)]
+pub struct List {
+    elements: RefCell<Vec<Rc<dyn Object>>>,
+}
+
+impl List {
+    pub fn new(elements: Vec<Rc<dyn Object>>) -> Self {
+        Self {
+            elements: RefCell::new(elements),
+        }
+    }
+
+    pub fn get_index(&self, index: &dyn Object) -> Result<Rc<dyn Object>> {
+        let elements = self.elements.borrow();
+        let i = position(index, elements.len())?;
+        Ok(elements[i].clone())
+    }
+
+    pub fn set_index(&self, index: &dyn Object, value: Rc<dyn Object>) -> Result<()> {
+        let mut elements = self.elements.borrow_mut();
+        let i = position(index, elements.len())?;
+        elements[i] = value;
+        Ok(())
+    }
+
+    pub fn elements(&self) -> Vec<Rc<dyn Object>> {
+        self.elements.borrow().clone()
+    }
+
+    fn len(&self) -> usize {
+        self.elements.borrow().len()
+    }
+
+    fn push(&self, value: Rc<dyn Object>) {
+        self.elements.borrow_mut().push(value);
+    }
+
+    fn pop(&self) -> Result<Rc<dyn Object>> {
+        self.elements
+            .borrow_mut()
+            .pop()
+            .ok_or_else(|| "Can't pop from an empty list.".into())
+    }
+
+    fn insert(&self, index: &dyn Object, value: Rc<dyn Object>) -> Result<()> {
+        // Inserting right after the last element is the same as pushing.
+        let i = position(index, self.len() + 1)?;
+        self.elements.borrow_mut().insert(i, value);
+        Ok(())
+    }
+
+    fn remove(&self, index: &dyn Object) -> Result<Rc<dyn Object>> {
+        let i = position(index, self.len())?;
+        Ok(self.elements.borrow_mut().remove(i))
+    }
+
+    fn slice(&self, start: &dyn Object, end: &dyn Object) -> Result<List> {
+        let end = position(end, self.len() + 1)?;
+        let start = position(start, end + 1)?;
+        Ok(List::new(self.elements.borrow()[start..end].to_vec()))
+    }
+
+    /// Compares elements the way `==` does, so `equals` methods count.
+    fn contains(
+        &self,
+        interpreter: &mut Interpreter,
+        token: &Token,
+        value: &Rc<dyn Object>,
+    ) -> Result<bool> {
+        for element in self.elements() {
+            if interpreter.equal(value, &element, token)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Converts a Lox value into a list position below `bound`.
+fn position(index: &dyn Object, bound: usize) -> Result<usize> {
+    let Some(&index) = index.as_any().downcast_ref::<f64>() else {
+        return Err("List index must be a number.".into());
+    };
+    if index.fract() != 0.0 {
+        Err("List index must be an integer.".into())
+    } else if index < 0.0 {
+        Err("List index can't be negative.".into())
+    } else if index >= bound as f64 {
+        Err("List index out of range.".into())
+    } else {
+        Ok(index as usize)
+    }
+}
+
+impl InstanceGet for Rc<List> {
+    fn get(&self, _interpreter: &mut Interpreter, name: &Token) -> Result<Rc<dyn Object>> {
+        let list = self.clone();
+        let method = match name.lexeme.as_str() {
+            "push" => NativeMethod::new("push", 1, move |_, _, args| {
+                list.push(args[0].clone());
+                Ok(Rc::new(Nil))
+            }),
+            "pop" => NativeMethod::new("pop", 0, move |_, _, _| list.pop()),
+            "len" => NativeMethod::new("len", 0, move |_, _, _| Ok(Rc::new(list.len() as f64))),
+            "insert" => NativeMethod::new("insert", 2, move |_, _, args| {
+                list.insert(&*args[0], args[1].clone())?;
+                Ok(Rc::new(Nil))
+            }),
+            "remove" => NativeMethod::new("remove", 1, move |_, _, args| list.remove(&*args[0])),
+            "slice" => NativeMethod::new("slice", 2, move |_, _, args| {
+                Ok(Rc::new(list.slice(&*args[0], &*args[1])?))
+            }),
+            "contains" => NativeMethod::new("contains", 1, move |interpreter, paren, args| {
+                Ok(Rc::new(list.contains(interpreter, paren, &args[0])?))
+            }),
+            _ => {
+                return Err(Error::runtime(
+                    name,
+                    format!("Undefined property '{}'.", name.lexeme),
+                ))
+            }
+        };
+        Ok(Rc::new(method))
+    }
+}
+
+impl Object for List {}
+
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(_guard) = PrintGuard::enter(self) else {
+            return write!(f, "[...]");
+        };
+        write!(f, "[")?;
+        for (i, element) in self.elements.borrow().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", element)?;
+        }
+        write!(f, "]")
+    }
+}
@@ -1,34 +1,43 @@
+use crate::error::Error;
 use crate::interpreter::Interpreter;
 use crate::parser::Parser;
-use crate::resolver::Resolver;
+use crate::resolver::{Locals, Resolver};
 use crate::scanner::Scanner;
+use crate::stmt::Stmt;
+use crate::token::{Span, Token, TokenType};
 use crate::Result;
-use std::cell::RefCell;
 use std::io::Write;
+use std::path::Path;
 use std::rc::Rc;
-use std::{fs, io, process};
+use std::{env, fs, io, process};
 
 #[derive(Default)]
 pub struct Lox {
     had_error: bool,
     had_runtime_error: bool,
-    interpreter: Rc<RefCell<Interpreter>>,
+    interpreter: Interpreter,
 }
 
 impl Lox {
     pub fn new() -> Self {
-        Default::default()
+        let mut lox = Self::default();
+        // Modules that aren't next to the importing file are looked up here.
+        if let Some(paths) = env::var_os("JLI_PATH") {
+            lox.interpreter
+                .set_search_path(env::split_paths(&paths).collect());
+        }
+        lox
     }
 
     pub fn run_file(&mut self, path: String) -> Result<()> {
-        let source = fs::read_to_string(path)?;
+        let source = fs::read_to_string(&path)?;
+        self.interpreter.set_script_path(Path::new(&path))?;
         self.run(source);
         // Indicate an error in the exit code.
         if self.had_error {
             // TODO: bubble up error to main and exit there.
             process::exit(65);
         } else if self.had_runtime_error {
-            // TODO: right now this is never true.
             process::exit(70);
         }
         Ok(())
@@ -45,36 +54,121 @@ impl Lox {
             }
             self.run(line);
             self.had_error = false;
+            self.had_runtime_error = false;
         }
         Ok(())
     }
 
     fn run(&mut self, source: String) {
-        let mut on_error = |line, message| {
-            self.report(line, "", message);
+        let source_id = self.interpreter.add_source(&source);
+        // Stop if there was a syntax or resolution error.
+        let Some((statements, locals)) = compile(&source, source_id, None) else {
+            self.had_error = true;
+            return;
         };
-        let mut scanner = Scanner::new(source, &mut on_error);
-        let tokens = scanner.scan_tokens();
-        let mut parser = Parser::new(tokens);
-        match parser.parse() {
-            // Stop if there was a syntax error.
-            Err(e) => {
-                eprintln!("{}", e);
-                process::exit(65);
-            }
-            Ok(statements) => {
-                let mut resolver = Resolver::new(self.interpreter.clone());
-                resolver.resolve(&statements).unwrap();
-                self.interpreter
-                    .borrow_mut()
-                    .interpret(&statements)
-                    .unwrap();
+        self.interpreter.resolve(locals);
+        let result = self.interpreter.interpret(statements);
+        if let Err(e) = result {
+            self.runtime_error(e);
+        }
+    }
+
+    fn runtime_error(&mut self, mut error: Error) {
+        if let Error::Throw { token, value, .. } = &mut error {
+            // Show the value the way `print` would, using its `toString()`.
+            if let Ok(string) = self.interpreter.stringify(value.clone(), token) {
+                *value = Rc::new(string);
             }
         }
+        eprintln!("{}", error);
+        if let Error::RuntimeError { token, .. } | Error::Throw { token, .. } = &error {
+            // The error may come from a module rather than the file being run.
+            let source = self.interpreter.source(token.span.source);
+            show_snippet(source, token.line, token.span);
+        }
+        self.had_runtime_error = true;
     }
+}
 
-    fn report(&mut self, line: usize, location: &str, message: &str) {
-        eprintln!("[line {}] Error{}: {}", line, location, message);
-        self.had_error = true;
+/// Scans, parses and resolves a program, reporting every error found.
+/// Errors in a module name its path. Returns `None` if there were any.
+pub fn compile(
+    source: &str,
+    source_id: usize,
+    module: Option<&str>,
+) -> Option<(Vec<Box<dyn Stmt>>, Locals)> {
+    let file = module.map_or(String::new(), |path| format!(" in '{}'", path));
+    let mut had_error = false;
+    let mut on_error = |line, span, message: &str| {
+        report(line, &file, message);
+        show_snippet(source, line, span);
+        had_error = true;
+    };
+    let mut scanner = Scanner::new(source, source_id, &mut on_error);
+    let tokens = scanner.scan_tokens();
+    let mut parser = Parser::new(tokens);
+    let (statements, errors) = parser.parse();
+    had_error |= !errors.is_empty();
+    for error in errors {
+        compile_error(source, &file, error);
+    }
+    if had_error {
+        return None;
     }
+    let mut resolver = Resolver::new();
+    let (locals, errors) = resolver.resolve_program(&statements);
+    if !errors.is_empty() {
+        for error in errors {
+            compile_error(source, &file, error);
+        }
+        return None;
+    }
+    Some((statements, locals))
+}
+
+fn error(source: &str, file: &str, token: &Token, message: &str) {
+    if token.token_type == TokenType::Eof {
+        report(token.line, &format!("{} at end", file), message);
+    } else {
+        let location = format!("{} at '{}'", file, token.lexeme);
+        report(token.line, &location, message);
+    }
+    show_snippet(source, token.line, token.span);
+}
+
+fn compile_error(source: &str, file: &str, error: Error) {
+    match error {
+        Error::ParseError { token, message } | Error::ResolveError { token, message } => {
+            self::error(source, file, &token, &message)
+        }
+        e => eprintln!("Error: {}", e),
+    }
+}
+
+fn report(line: usize, location: &str, message: &str) {
+    eprintln!("[line {}] Error{}: {}", line, location, message);
+}
+
+/// Prints the source line containing `span` with carets under the span.
+fn show_snippet(source: &str, line: usize, span: Span) {
+    let (Some(before), Some(after)) = (source.get(..span.start), source.get(span.start..)) else {
+        return;
+    };
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line_end = after.find('\n').map_or(source.len(), |i| span.start + i);
+    let text = &source[line_start..line_end];
+    // Keep tabs so the carets line up with the text above them.
+    let padding: String = text
+        .chars()
+        .take(span.column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let width = source
+        .get(span.start..span.end.min(line_end))
+        .map_or(0, |lexeme| lexeme.chars().count())
+        .max(1);
+    let gutter = " ".repeat(line.to_string().len());
+    eprintln!("{} |", gutter);
+    eprintln!("{} | {}", line, text);
+    eprintln!("{} | {}{}", gutter, padding, "^".repeat(width));
 }
@@ -3,7 +3,7 @@ macro_rules! ast {
     ($trait:ident -> $vr:ty [$($s:ident($($f:ident : $t:ty),*)),+$(,)*]) => {
         use paste::paste;
 
-        pub trait $trait: std::fmt::Debug + crate::object::AsAny {
+        pub trait $trait: std::fmt::Debug + $crate::object::AsAny {
             fn accept(&self, visitor: &mut dyn Visitor<$vr>) -> $vr;
         }
 
@@ -7,11 +7,15 @@ mod callable;
 mod class;
 mod environment;
 mod error;
+mod exception;
 mod expr;
 mod instance;
 mod interpreter;
+mod list;
 mod lox;
 mod macros;
+mod map;
+mod module;
 mod object;
 mod parser;
 mod resolver;
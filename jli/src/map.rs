@@ -0,0 +1,202 @@
+use crate::callable::NativeMethod;
+use crate::error::Error;
+use crate::instance::InstanceGet;
+use crate::interpreter::Interpreter;
+use crate::list::List;
+use crate::object::{Object, PrintGuard};
+use crate::token::Token;
+use crate::Result;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::ptr;
+use std::rc::Rc;
+
+/// Keeps its entries in insertion order so that printing and iterating a map
+/// are predictable. Keys are hashed and compared the way `==` compares them,
+/// which can call back into Lox, so no borrow is held across those calls.
+#[derive(Debug, Default)]
+pub struct Map {
+    /// Positions in `entries`, grouped by the hash of their key.
+    indices: RefCell<HashMap<u64, Vec<usize>>>,
+    entries: RefCell<Vec<Entry>>,
+    /// Counts changes, so that a lookup can tell if a key's `equals` method
+    /// changed the map and the positions it found are no longer valid.
+    version: Cell<usize>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    key: Rc<dyn Object>,
+    value: Rc<dyn Object>,
+}
+
+impl Map {
+    /// Returns the hash of `key` and the position of its entry, if any.
+    fn find(
+        &self,
+        interpreter: &mut Interpreter,
+        token: &Token,
+        key: &Rc<dyn Object>,
+    ) -> Result<(u64, Option<usize>)> {
+        let hash = interpreter.hash(key, token)?;
+        let version = self.version.get();
+        let candidates = self.indices.borrow().get(&hash).cloned();
+        for i in candidates.unwrap_or_default() {
+            let existing = self.entries.borrow()[i].key.clone();
+            let equal = interpreter.equal(key, &existing, token)?;
+            if self.version.get() != version {
+                let message = "Map changed while looking up a key.";
+                return Err(Error::runtime(token, message));
+            }
+            if equal {
+                return Ok((hash, Some(i)));
+            }
+        }
+        Ok((hash, None))
+    }
+
+    fn changed(&self) {
+        self.version.set(self.version.get().wrapping_add(1));
+    }
+
+    pub fn get_index(
+        &self,
+        interpreter: &mut Interpreter,
+        token: &Token,
+        key: Rc<dyn Object>,
+    ) -> Result<Rc<dyn Object>> {
+        match self.find(interpreter, token, &key)? {
+            (_, Some(i)) => Ok(self.entries.borrow()[i].value.clone()),
+            (_, None) => Err(undefined_key(&*key)),
+        }
+    }
+
+    pub fn set_index(
+        &self,
+        interpreter: &mut Interpreter,
+        token: &Token,
+        key: Rc<dyn Object>,
+        value: Rc<dyn Object>,
+    ) -> Result<()> {
+        match self.find(interpreter, token, &key)? {
+            (_, Some(i)) => self.entries.borrow_mut()[i].value = value,
+            (hash, None) => {
+                self.changed();
+                let mut entries = self.entries.borrow_mut();
+                let mut indices = self.indices.borrow_mut();
+                indices.entry(hash).or_default().push(entries.len());
+                entries.push(Entry { key, value });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn entries(&self) -> Vec<(Rc<dyn Object>, Rc<dyn Object>)> {
+        let entries = self.entries.borrow();
+        entries
+            .iter()
+            .map(|entry| (entry.key.clone(), entry.value.clone()))
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    pub fn keys(&self) -> List {
+        let entries = self.entries.borrow();
+        List::new(entries.iter().map(|entry| entry.key.clone()).collect())
+    }
+
+    fn values(&self) -> List {
+        let entries = self.entries.borrow();
+        List::new(entries.iter().map(|entry| entry.value.clone()).collect())
+    }
+
+    fn has(
+        &self,
+        interpreter: &mut Interpreter,
+        token: &Token,
+        key: Rc<dyn Object>,
+    ) -> Result<bool> {
+        Ok(self.find(interpreter, token, &key)?.1.is_some())
+    }
+
+    fn remove(
+        &self,
+        interpreter: &mut Interpreter,
+        token: &Token,
+        key: Rc<dyn Object>,
+    ) -> Result<Rc<dyn Object>> {
+        let (hash, Some(removed)) = self.find(interpreter, token, &key)? else {
+            return Err(undefined_key(&*key));
+        };
+        self.changed();
+        let mut indices = self.indices.borrow_mut();
+        if let Some(bucket) = indices.get_mut(&hash) {
+            bucket.retain(|&i| i != removed);
+            if bucket.is_empty() {
+                indices.remove(&hash);
+            }
+        }
+        for i in indices.values_mut().flatten() {
+            if *i > removed {
+                *i -= 1;
+            }
+        }
+        Ok(self.entries.borrow_mut().remove(removed).value)
+    }
+}
+
+fn undefined_key(key: &dyn Object) -> Error {
+    format!("Undefined key '{}'.", key).into()
+}
+
+impl InstanceGet for Rc<Map> {
+    fn get(&self, _interpreter: &mut Interpreter, name: &Token) -> Result<Rc<dyn Object>> {
+        let map = self.clone();
+        let method = match name.lexeme.as_str() {
+            "keys" => NativeMethod::new("keys", 0, move |_, _, _| Ok(Rc::new(map.keys()))),
+            "values" => NativeMethod::new("values", 0, move |_, _, _| Ok(Rc::new(map.values()))),
+            "has" => NativeMethod::new("has", 1, move |interpreter, paren, args| {
+                Ok(Rc::new(map.has(interpreter, paren, args[0].clone())?))
+            }),
+            "remove" => NativeMethod::new("remove", 1, move |interpreter, paren, args| {
+                map.remove(interpreter, paren, args[0].clone())
+            }),
+            "len" => NativeMethod::new("len", 0, move |_, _, _| Ok(Rc::new(map.len() as f64))),
+            _ => {
+                return Err(Error::runtime(
+                    name,
+                    format!("Undefined property '{}'.", name.lexeme),
+                ))
+            }
+        };
+        Ok(Rc::new(method))
+    }
+}
+
+impl Object for Map {}
+
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(_guard) = PrintGuard::enter(self) else {
+            return write!(f, "{{...}}");
+        };
+        write!(f, "{{")?;
+        for (i, entry) in self.entries.borrow().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: {}", entry.key, entry.value)?;
+        }
+        write!(f, "}}")
+    }
+}
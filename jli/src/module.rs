@@ -0,0 +1,56 @@
+use crate::environment::Environment;
+use crate::error::Error;
+use crate::instance::InstanceGet;
+use crate::interpreter::Interpreter;
+use crate::object::Object;
+use crate::stmt::Stmt;
+use crate::token::Token;
+use crate::Result;
+use std::fmt;
+use std::ptr;
+use std::rc::Rc;
+
+/// An imported file, whose top-level definitions are its properties.
+#[derive(Debug)]
+pub struct Module {
+    name: String,
+    globals: Rc<Environment>,
+    // Resolved locals are keyed by the address of these statements' nodes,
+    // so they must live as long as the module's functions can be called.
+    _statements: Vec<Box<dyn Stmt>>,
+}
+
+impl Module {
+    pub fn new(name: String, globals: Rc<Environment>, statements: Vec<Box<dyn Stmt>>) -> Self {
+        Self {
+            name,
+            globals,
+            _statements: statements,
+        }
+    }
+}
+
+impl InstanceGet for Rc<Module> {
+    fn get(&self, _interpreter: &mut Interpreter, name: &Token) -> Result<Rc<dyn Object>> {
+        self.globals.get(name).map_err(|_| {
+            Error::runtime(
+                name,
+                format!("Module '{}' has no '{}'.", self.name, name.lexeme),
+            )
+        })
+    }
+}
+
+impl Object for Module {}
+
+impl PartialEq for Module {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
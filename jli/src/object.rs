@@ -1,7 +1,10 @@
 use crate::expr::VisitorResult;
 use crate::Result;
 use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::fmt;
+use std::hash::Hasher;
 use std::rc::Rc;
 
 const OPS_NUMBERS: &str = "Operands must be numbers.";
@@ -47,6 +50,50 @@ pub trait Object: fmt::Display + fmt::Debug + ObjectEq {
     fn try_mul(&self, _other: &dyn Object) -> Result<f64> {
         Err(OPS_NUMBERS.into())
     }
+
+    fn try_rem(&self, _other: &dyn Object) -> Result<f64> {
+        Err(OPS_NUMBERS.into())
+    }
+
+    fn try_int_div(&self, _other: &dyn Object) -> Result<f64> {
+        Err(OPS_NUMBERS.into())
+    }
+
+    fn try_pow(&self, _other: &dyn Object) -> Result<f64> {
+        Err(OPS_NUMBERS.into())
+    }
+
+    /// Feeds the value to `state` so that it can be used as a map key.
+    /// Values that are `==` must hash the same.
+    fn try_hash(&self, _state: &mut dyn Hasher) -> Result<()> {
+        Err("Only numbers, strings, booleans, nil and instances can be map keys.".into())
+    }
+}
+
+thread_local! {
+    static PRINTING: RefCell<HashSet<usize>> = RefCell::new(HashSet::new());
+}
+
+/// Marks a collection as being printed until dropped, so that one which
+/// contains itself prints a placeholder instead of recursing forever.
+pub struct PrintGuard {
+    address: usize,
+}
+
+impl PrintGuard {
+    /// Returns `None` if `collection` is already being printed.
+    pub fn enter<T>(collection: &T) -> Option<Self> {
+        let address = collection as *const T as usize;
+        let entered = PRINTING.with(|printing| printing.borrow_mut().insert(address));
+        // Not `then_some`, which would drop a guard and unmark the collection.
+        entered.then(|| Self { address })
+    }
+}
+
+impl Drop for PrintGuard {
+    fn drop(&mut self) {
+        PRINTING.with(|printing| printing.borrow_mut().remove(&self.address));
+    }
 }
 
 pub trait AsAny {
@@ -138,12 +185,45 @@ impl Object for f64 {
         }
         Err(OPS_NUMBERS.into())
     }
+
+    fn try_rem(&self, other: &dyn Object) -> Result<f64> {
+        if let Some(o) = other.as_any().downcast_ref() {
+            return Ok(self % o);
+        }
+        Err(OPS_NUMBERS.into())
+    }
+
+    fn try_int_div(&self, other: &dyn Object) -> Result<f64> {
+        if let Some(o) = other.as_any().downcast_ref::<f64>() {
+            return Ok((self / o).floor());
+        }
+        Err(OPS_NUMBERS.into())
+    }
+
+    fn try_pow(&self, other: &dyn Object) -> Result<f64> {
+        if let Some(o) = other.as_any().downcast_ref() {
+            return Ok(self.powf(*o));
+        }
+        Err(OPS_NUMBERS.into())
+    }
+
+    fn try_hash(&self, state: &mut dyn Hasher) -> Result<()> {
+        // -0 and 0 are equal but have different bits.
+        let n = if *self == 0.0 { 0.0 } else { *self };
+        state.write_u64(n.to_bits());
+        Ok(())
+    }
 }
 
 impl Object for bool {
     fn truthy(&self) -> bool {
         *self
     }
+
+    fn try_hash(&self, state: &mut dyn Hasher) -> Result<()> {
+        state.write_u8(*self as u8);
+        Ok(())
+    }
 }
 
 impl Object for String {
@@ -153,15 +233,24 @@ impl Object for String {
         }
         Err(OPS_ADD.into())
     }
+
+    fn try_hash(&self, state: &mut dyn Hasher) -> Result<()> {
+        state.write(self.as_bytes());
+        Ok(())
+    }
 }
 
 impl Object for Nil {
     fn truthy(&self) -> bool {
         false
     }
+
+    fn try_hash(&self, _state: &mut dyn Hasher) -> Result<()> {
+        Ok(())
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub struct Nil;
 
 impl fmt::Display for Nil {
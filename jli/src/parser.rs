@@ -1,52 +1,72 @@
 use crate::error::Error;
 use crate::expr::{
-    Assign, Binary, Call, Expr, Get, Grouping, Literal, Logical, Set, Super, This, Unary, Variable,
+    Assign, Binary, Call, Comma, Conditional, Expr, Get, Grouping, Index, Lambda, List, Literal,
+    Logical, Map, Set, SetIndex, Stringify, Super, This, Unary, Variable,
 };
 use crate::object::Nil;
-use crate::stmt::{Block, Class, Expression, Function, If, Print, Return, Stmt, Var, While};
+use crate::stmt::{
+    Block, Break, Case, Class, Const, Continue, Expression, ForIn, Function, If, Import, Match,
+    Parameter, Parameters, Pattern, Print, Return, Stmt, Throw, Try, Var, While,
+};
 use crate::token::TokenType::*;
 use crate::token::{Token, TokenType};
 use crate::Result;
-use std::mem::discriminant;
+use std::mem::{self, discriminant};
 use std::rc::Rc;
 
-// TODO: receive on_error callback to report errors.
 pub struct Parser<'p> {
     tokens: &'p [Token],
     current: usize,
+    errors: Vec<Error>,
 }
 
 type ExprResult = Result<Box<dyn Expr>>;
 type StmtResult = Result<Box<dyn Stmt>>;
+type FunctionResult = Result<(Parameters, Vec<Rc<dyn Stmt>>)>;
 
 impl<'p> Parser<'p> {
     pub fn new(tokens: &'p [Token]) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            errors: vec![],
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Box<dyn Stmt>>> {
+    /// Parses as many statements as possible, recovering from syntax errors
+    /// so that all of them can be reported at once.
+    pub fn parse(&mut self) -> (Vec<Box<dyn Stmt>>, Vec<Error>) {
         let mut statements = vec![];
         while !self.is_at_end() {
-            statements.push(self.declaration()?);
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
         }
-        Ok(statements)
+        (statements, mem::take(&mut self.errors))
     }
 
-    fn declaration(&mut self) -> StmtResult {
+    fn declaration(&mut self) -> Option<Box<dyn Stmt>> {
         let result: StmtResult = if self.matches(&[Class]) {
             self.class_declaration()
-        } else if self.matches(&[Fun]) {
-            Ok(Box::new(self.function("function")?))
+        } else if self.check(&Fun) && self.check_next(&Identifier(Default::default())) {
+            self.advance();
+            self.function("function")
+                .map(|f| Box::new(f) as Box<dyn Stmt>)
+        } else if self.matches(&[Import]) {
+            self.import_declaration()
+        } else if self.matches(&[Const]) {
+            self.const_declaration()
         } else if self.matches(&[Var]) {
             self.var_declaration()
         } else {
             self.statement()
         };
         match result {
-            Ok(s) => Ok(s),
+            Ok(s) => Some(s),
             Err(e) => {
+                self.errors.push(e);
                 self.synchronize();
-                Err(e)
+                None
             }
         }
     }
@@ -61,22 +81,89 @@ impl<'p> Parser<'p> {
         };
         self.consume(&LeftBrace, "Expect '{' before class body.")?;
         let mut methods = vec![];
+        let mut class_methods = vec![];
+        let mut getters = vec![];
+        let mut setters = vec![];
         while !self.check(&RightBrace) && !self.is_at_end() {
-            methods.push(Rc::new(self.function("method")?));
+            if self.matches(&[Class]) {
+                class_methods.push(Rc::new(self.function("method")?));
+            } else if self.peek().lexeme == "set"
+                && self.check_next(&Identifier(Default::default()))
+            {
+                self.advance();
+                let setter = self.function("setter")?;
+                if setter.params.positional.len() != 1 || setter.params.rest.is_some() {
+                    let error =
+                        self.error(&setter.name, "A setter must have exactly one parameter.");
+                    self.errors.push(error);
+                }
+                setters.push(Rc::new(setter));
+            } else if self.check_next(&LeftBrace) {
+                // Getters are declared without a parameter list.
+                let name = self.consume(&Identifier(Default::default()), "Expect method name.")?;
+                self.advance();
+                let body = self.block()?.into_iter().map(From::from).collect();
+                getters.push(Rc::new(Function::new(name, Parameters::default(), body)));
+            } else {
+                methods.push(Rc::new(self.function("method")?));
+            }
         }
         self.consume(&RightBrace, "Expect '}' after class body.")?;
-        Ok(Class::boxed(name, superclass, methods))
+        Ok(Class::boxed(
+            name,
+            superclass,
+            methods,
+            class_methods,
+            getters,
+            setters,
+        ))
+    }
+
+    /// Parses `import "path" as name;` and `import "path" for a, b;`.
+    fn import_declaration(&mut self) -> StmtResult {
+        let path = self.consume(&LoxString(Default::default()), "Expect module path.")?;
+        let alias = if self.peek().lexeme == "as" {
+            self.advance();
+            Some(self.consume(
+                &Identifier(Default::default()),
+                "Expect module name after 'as'.",
+            )?)
+        } else {
+            None
+        };
+        let mut names = vec![];
+        if self.matches(&[For]) {
+            loop {
+                names
+                    .push(self.consume(&Identifier(Default::default()), "Expect name to import.")?);
+                if !self.matches(&[Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(&Semicolon, "Expect ';' after import.")?;
+        Ok(Import::boxed(path, alias, names))
     }
 
     fn statement(&mut self) -> StmtResult {
-        if self.matches(&[For]) {
+        if self.matches(&[Break]) {
+            self.break_statement()
+        } else if self.matches(&[Continue]) {
+            self.continue_statement()
+        } else if self.matches(&[For]) {
             self.for_statement()
         } else if self.matches(&[If]) {
             self.if_statement()
+        } else if self.matches(&[Match]) {
+            self.match_statement()
         } else if self.matches(&[Print]) {
             self.print_statement()
         } else if self.matches(&[Return]) {
             self.return_statement()
+        } else if self.matches(&[Throw]) {
+            self.throw_statement()
+        } else if self.matches(&[Try]) {
+            self.try_statement()
         } else if self.matches(&[While]) {
             self.while_statement()
         } else if self.matches(&[LeftBrace]) {
@@ -86,8 +173,26 @@ impl<'p> Parser<'p> {
         }
     }
 
+    fn break_statement(&mut self) -> StmtResult {
+        let keyword = self.previous();
+        self.consume(&Semicolon, "Expect ';' after 'break'.")?;
+        Ok(Break::boxed(keyword))
+    }
+
+    fn continue_statement(&mut self) -> StmtResult {
+        let keyword = self.previous();
+        self.consume(&Semicolon, "Expect ';' after 'continue'.")?;
+        Ok(Continue::boxed(keyword))
+    }
+
     fn for_statement(&mut self) -> StmtResult {
         self.consume(&LeftParen, "Expect '(' after 'for'.")?;
+        // Telling a for-in loop apart needs a look past the variable name.
+        let is_for_in = self.check(&Var)
+            && matches!(self.tokens.get(self.current + 2), Some(t) if t.token_type == In);
+        if is_for_in {
+            return self.for_in_statement();
+        }
         let initializer = if self.matches(&[Semicolon]) {
             None
         } else if self.matches(&[Var]) {
@@ -109,16 +214,23 @@ impl<'p> Parser<'p> {
         self.consume(&RightParen, "Expect ')' after for clauses.")?;
         let mut body = self.statement()?;
 
-        if let Some(inc) = increment {
-            body = Block::boxed(vec![body, Expression::boxed(inc)]);
-        }
-        body = While::boxed(condition, body);
+        body = While::boxed(condition, body, increment);
         if let Some(init) = initializer {
             body = Block::boxed(vec![init, body]);
         }
         Ok(body)
     }
 
+    fn for_in_statement(&mut self) -> StmtResult {
+        self.consume(&Var, "Expect 'var' in for-in loop.")?;
+        let name = self.consume(&Identifier(Default::default()), "Expect variable name.")?;
+        let keyword = self.consume(&In, "Expect 'in' after variable name.")?;
+        let iterable = self.expression()?;
+        self.consume(&RightParen, "Expect ')' after for-in clause.")?;
+        let body = self.statement()?;
+        Ok(ForIn::boxed(name, keyword, iterable, body))
+    }
+
     fn if_statement(&mut self) -> StmtResult {
         self.consume(&LeftParen, "Expect '(' after 'if'.")?;
         let condition = self.expression()?;
@@ -133,9 +245,10 @@ impl<'p> Parser<'p> {
     }
 
     fn print_statement(&mut self) -> StmtResult {
+        let keyword = self.previous();
         let value = self.expression()?;
         self.consume(&Semicolon, "Expect ';' after value.")?;
-        Ok(Print::boxed(value))
+        Ok(Print::boxed(keyword, value))
     }
 
     fn return_statement(&mut self) -> StmtResult {
@@ -149,13 +262,149 @@ impl<'p> Parser<'p> {
         Ok(Return::boxed(keyword, value))
     }
 
+    fn throw_statement(&mut self) -> StmtResult {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.consume(&Semicolon, "Expect ';' after thrown value.")?;
+        Ok(Throw::boxed(keyword, value))
+    }
+
+    fn try_statement(&mut self) -> StmtResult {
+        self.consume(&LeftBrace, "Expect '{' after 'try'.")?;
+        let body = self.block()?;
+        let catch = if self.matches(&[Catch]) {
+            self.consume(&LeftParen, "Expect '(' after 'catch'.")?;
+            let name = self.consume(&Identifier(Default::default()), "Expect variable name.")?;
+            self.consume(&RightParen, "Expect ')' after catch variable.")?;
+            self.consume(&LeftBrace, "Expect '{' before catch body.")?;
+            Some((name, self.block()?))
+        } else {
+            None
+        };
+        let finally = if self.matches(&[Finally]) {
+            self.consume(&LeftBrace, "Expect '{' after 'finally'.")?;
+            Some(self.block()?)
+        } else {
+            None
+        };
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error(&self.peek(), "Expect 'catch' or 'finally' after try block."));
+        }
+        Ok(Try::boxed(body, catch, finally))
+    }
+
+    fn match_statement(&mut self) -> StmtResult {
+        self.consume(&LeftParen, "Expect '(' after 'match'.")?;
+        let subject = self.expression()?;
+        self.consume(&RightParen, "Expect ')' after match value.")?;
+        self.consume(&LeftBrace, "Expect '{' before match cases.")?;
+        let mut cases = vec![];
+        while !self.check(&RightBrace) && !self.is_at_end() {
+            let keyword = self.consume(&Case, "Expect 'case' in match body.")?;
+            let mut patterns = vec![self.pattern()?];
+            while self.matches(&[Comma]) {
+                patterns.push(self.pattern()?);
+            }
+            let guard = if self.matches(&[If]) {
+                Some(self.expression()?)
+            } else {
+                None
+            };
+            self.consume(&EqualGreater, "Expect '=>' after case pattern.")?;
+            let body = self.statement()?;
+            cases.push(Case {
+                keyword,
+                patterns,
+                guard,
+                body,
+            });
+        }
+        self.consume(&RightBrace, "Expect '}' after match cases.")?;
+        Ok(Match::boxed(subject, cases))
+    }
+
+    fn pattern(&mut self) -> Result<Pattern> {
+        if self.matches(&[Identifier(Default::default())]) {
+            let name = self.previous();
+            if name.lexeme == "_" {
+                Ok(Pattern::Wildcard)
+            } else if self.matches(&[LeftParen]) {
+                let mut fields = vec![];
+                if !self.check(&RightParen) {
+                    loop {
+                        let field =
+                            self.consume(&Identifier(Default::default()), "Expect field name.")?;
+                        let pattern = if self.matches(&[Colon]) {
+                            self.pattern()?
+                        } else {
+                            Pattern::Binding(field.clone())
+                        };
+                        fields.push((field, pattern));
+                        if !self.matches(&[Comma]) {
+                            break;
+                        }
+                    }
+                }
+                self.consume(&RightParen, "Expect ')' after field patterns.")?;
+                Ok(Pattern::Class(Variable::new(name), fields))
+            } else {
+                Ok(Pattern::Binding(name))
+            }
+        } else if self.matches(&[True]) {
+            Ok(Pattern::Literal(Rc::new(true)))
+        } else if self.matches(&[False]) {
+            Ok(Pattern::Literal(Rc::new(false)))
+        } else if self.matches(&[TokenType::Nil]) {
+            Ok(Pattern::Literal(Rc::new(Nil)))
+        } else if let LoxString(s) = self.peek().token_type {
+            self.advance();
+            Ok(Pattern::Literal(Rc::new(s)))
+        } else if self.check(&Minus) || self.check(&Number(Default::default())) {
+            let low = self.pattern_number()?;
+            if self.matches(&[DotDot]) {
+                Ok(Pattern::Range(low, self.pattern_number()?))
+            } else {
+                Ok(Pattern::Literal(Rc::new(low)))
+            }
+        } else {
+            Err(self.error(&self.peek(), "Expect pattern."))
+        }
+    }
+
+    fn pattern_number(&mut self) -> Result<f64> {
+        let negative = self.matches(&[Minus]);
+        let token = self.consume(&Number(Default::default()), "Expect number.")?;
+        match token.token_type {
+            Number(n) if negative => Ok(-n),
+            Number(n) => Ok(n),
+            _ => Err("not a number".into()),
+        }
+    }
+
+    fn const_declaration(&mut self) -> StmtResult {
+        let name = self.consume(&Identifier(Default::default()), "Expect constant name.")?;
+        self.consume(&Equal, "Expect '=' after constant name.")?;
+        let initializer = self.assignment()?;
+        if self.check(&Comma) {
+            let message = "Can't declare multiple constants in one 'const' statement.";
+            return Err(self.error(&self.peek(), message));
+        }
+        self.consume(&Semicolon, "Expect ';' after constant declaration.")?;
+        Ok(Const::boxed(name, initializer))
+    }
+
     fn var_declaration(&mut self) -> StmtResult {
         let name = self.consume(&Identifier(Default::default()), "Expect variable name.")?;
         let initializer = if self.matches(&[Equal]) {
-            Some(self.expression()?)
+            // Not expression(), so a comma can't sneak in as an operator.
+            Some(self.assignment()?)
         } else {
             None
         };
+        if self.check(&Comma) {
+            let message = "Can't declare multiple variables in one 'var' statement.";
+            return Err(self.error(&self.peek(), message));
+        }
         self.consume(&Semicolon, "Expect ';' after variable declaration.")?;
         Ok(Var::boxed(name, initializer))
     }
@@ -165,7 +414,7 @@ impl<'p> Parser<'p> {
         let condition = self.expression()?;
         self.consume(&RightParen, "Expect ')' after condition.")?;
         let body = self.statement()?;
-        Ok(While::boxed(condition, body))
+        Ok(While::boxed(condition, body, None))
     }
 
     fn expression_statement(&mut self) -> StmtResult {
@@ -180,14 +429,45 @@ impl<'p> Parser<'p> {
             &format!("Expect {} name.", kind),
         )?;
         self.consume(&LeftParen, &format!("Expect '(' after {} name.", kind))?;
-        let mut parameters = vec![];
+        let (parameters, body) = self.function_body(kind)?;
+        Ok(Function::new(name, parameters, body))
+    }
+
+    /// Parses a function's parameters and body, after the '('.
+    fn function_body(&mut self, kind: &str) -> FunctionResult {
+        let mut parameters = Parameters::default();
         if !self.check(&RightParen) {
             loop {
-                if parameters.len() >= 255 {
-                    self.error(&self.peek(), "Can't have more than 255 parameters.");
+                if parameters.positional.len() >= 255 {
+                    let error = self.error(&self.peek(), "Can't have more than 255 parameters.");
+                    self.errors.push(error);
                 }
-                let p = self.consume(&Identifier(Default::default()), "Expect parameter name.")?;
-                parameters.push(p);
+                if self.matches(&[DotDotDot]) {
+                    let rest = self.consume(
+                        &Identifier(Default::default()),
+                        "Expect rest parameter name after '...'.",
+                    )?;
+                    parameters.rest = Some(rest);
+                    if !self.check(&RightParen) {
+                        return Err(self.error(&self.peek(), "Rest parameter must be last."));
+                    }
+                    break;
+                }
+                let name =
+                    self.consume(&Identifier(Default::default()), "Expect parameter name.")?;
+                let default = if self.matches(&[Equal]) {
+                    Some(self.assignment()?.into())
+                } else {
+                    let after_default = parameters.positional.iter().any(|p| p.default.is_some());
+                    if after_default {
+                        let message =
+                            "A parameter without a default can't follow one with a default.";
+                        let error = self.error(&name, message);
+                        self.errors.push(error);
+                    }
+                    None
+                };
+                parameters.positional.push(Parameter { name, default });
                 if !self.matches(&[Comma]) {
                     break;
                 }
@@ -197,30 +477,84 @@ impl<'p> Parser<'p> {
         self.consume(&LeftBrace, &format!("Expect '{{' before {} body.", kind))?;
         // Convert from a Vec<Box> into a Vec<Rc>.
         let body = self.block()?.into_iter().map(From::from).collect();
-        Ok(Function::new(name, parameters, body))
+        Ok((parameters, body))
     }
 
     fn block(&mut self) -> Result<Vec<Box<dyn Stmt>>> {
         let mut statements = vec![];
         while !self.check(&RightBrace) && !self.is_at_end() {
-            statements.push(self.declaration()?);
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
         }
         self.consume(&RightBrace, "Expect '}' after block.")?;
         Ok(statements)
     }
 
+    fn comma(&mut self) -> ExprResult {
+        let mut expr = self.assignment()?;
+        while self.matches(&[Comma]) {
+            let right = self.assignment()?;
+            expr = Comma::boxed(expr, right);
+        }
+        Ok(expr)
+    }
+
     fn assignment(&mut self) -> ExprResult {
-        let expr = self.or()?;
-        if self.matches(&[Equal]) {
-            let equals = self.previous();
+        let expr = self.conditional()?;
+        if self.matches(&[
+            Equal,
+            PlusEqual,
+            MinusEqual,
+            StarEqual,
+            SlashEqual,
+            PercentEqual,
+        ]) {
+            let operator = self.previous();
             let value = self.assignment()?;
-            let expr_any = (*expr).as_any();
-            if let Some(v) = expr_any.downcast_ref::<Variable>() {
-                return Ok(Assign::boxed(v.name.clone(), value));
-            } else if let Some(g) = expr_any.downcast_ref::<Get>() {
-                return Ok(Set::boxed(g.object.clone(), g.name.clone(), value));
-            }
-            self.error(&equals, "Invalid assignment target.");
+            return Ok(self.assign_to(expr, operator, value));
+        }
+        Ok(expr)
+    }
+
+    /// Builds an assignment to `target`, which must be a variable or a
+    /// property, reporting an error and returning `target` otherwise.
+    fn assign_to(
+        &mut self,
+        target: Box<dyn Expr>,
+        operator: Token,
+        value: Box<dyn Expr>,
+    ) -> Box<dyn Expr> {
+        let target_any = (*target).as_any();
+        if let Some(v) = target_any.downcast_ref::<Variable>() {
+            return Assign::boxed(v.name.clone(), operator, value);
+        } else if let Some(g) = target_any.downcast_ref::<Get>() {
+            return Set::boxed(g.object.clone(), g.name.clone(), operator, value);
+        } else if let Some(i) = target_any.downcast_ref::<Index>() {
+            return SetIndex::boxed(
+                i.object.clone(),
+                i.bracket.clone(),
+                i.index.clone(),
+                operator,
+                value,
+            );
+        }
+        let error = self.error(&operator, "Invalid assignment target.");
+        self.errors.push(error);
+        target
+    }
+
+    fn conditional(&mut self) -> ExprResult {
+        let expr = self.or()?;
+        if self.matches(&[Question]) {
+            let then_branch = self.expression()?;
+            self.consume(
+                &Colon,
+                "Expect ':' after then branch of conditional expression.",
+            )?;
+            // Recurse so that `a ? b : c ? d : e` groups to the right.
+            let else_branch = self.conditional()?;
+            return Ok(Conditional::boxed(expr, then_branch, else_branch));
         }
         Ok(expr)
     }
@@ -247,7 +581,7 @@ impl<'p> Parser<'p> {
     }
 
     fn expression(&mut self) -> ExprResult {
-        self.assignment()
+        self.comma()
     }
 
     fn equality(&mut self) -> ExprResult {
@@ -282,7 +616,7 @@ impl<'p> Parser<'p> {
 
     fn factor(&mut self) -> ExprResult {
         let mut expr = self.unary()?;
-        while self.matches(&[Slash, Star]) {
+        while self.matches(&[Percent, Slash, Star, TildeSlash]) {
             let operator = self.previous();
             let right = self.unary()?;
             expr = Binary::boxed(expr, operator, right);
@@ -296,7 +630,40 @@ impl<'p> Parser<'p> {
             let right = self.unary()?;
             return Ok(Unary::boxed(operator, right));
         }
-        self.call()
+        if self.matches(&[PlusPlus, MinusMinus]) {
+            // `++x` is `x += 1`.
+            let operator = self.previous();
+            let token_type = if operator.token_type == PlusPlus {
+                PlusEqual
+            } else {
+                MinusEqual
+            };
+            let target = self.unary()?;
+            let operator = Token::new(token_type, operator.lexeme, operator.line, operator.span);
+            return Ok(self.assign_to(target, operator, Literal::boxed(Rc::new(1.0))));
+        }
+        self.power()
+    }
+
+    /// Exponentiation binds tighter than unary minus, so `-2 ** 2` is -4, and
+    /// groups to the right.
+    fn power(&mut self) -> ExprResult {
+        let expr = self.postfix()?;
+        if self.matches(&[StarStar]) {
+            let operator = self.previous();
+            let right = self.unary()?;
+            return Ok(Binary::boxed(expr, operator, right));
+        }
+        Ok(expr)
+    }
+
+    fn postfix(&mut self) -> ExprResult {
+        let expr = self.call()?;
+        if self.matches(&[PlusPlus, MinusMinus]) {
+            let operator = self.previous();
+            return Ok(self.assign_to(expr, operator, Literal::boxed(Rc::new(1.0))));
+        }
+        Ok(expr)
     }
 
     fn call(&mut self) -> ExprResult {
@@ -310,6 +677,10 @@ impl<'p> Parser<'p> {
                     "Expect property name after '.'.",
                 )?;
                 expr = Get::boxed(expr.into(), name);
+            } else if self.matches(&[LeftBracket]) {
+                let index = self.expression()?;
+                let bracket = self.consume(&RightBracket, "Expect ']' after index.")?;
+                expr = Index::boxed(expr.into(), bracket, index.into());
             } else {
                 break;
             }
@@ -319,19 +690,33 @@ impl<'p> Parser<'p> {
 
     fn finish_call(&mut self, callee: Box<dyn Expr>) -> ExprResult {
         let mut arguments = vec![];
+        let mut named = vec![];
         if !self.check(&RightParen) {
             loop {
-                if arguments.len() >= 255 {
-                    self.error(&self.peek(), "Can't have more than 255 arguments.");
+                if arguments.len() + named.len() >= 255 {
+                    let error = self.error(&self.peek(), "Can't have more than 255 arguments.");
+                    self.errors.push(error);
+                }
+                // Commas here separate arguments rather than being operators.
+                if self.check(&Identifier(Default::default())) && self.check_next(&Colon) {
+                    let name = self.advance();
+                    self.advance();
+                    named.push((name, self.assignment()?));
+                } else {
+                    if !named.is_empty() {
+                        let message = "Positional arguments can't follow named ones.";
+                        let error = self.error(&self.peek(), message);
+                        self.errors.push(error);
+                    }
+                    arguments.push(self.assignment()?);
                 }
-                arguments.push(self.expression()?);
                 if !self.matches(&[Comma]) {
                     break;
                 }
             }
         }
         let paren = self.consume(&RightParen, "Expect ')' after arguments.")?;
-        Ok(Call::boxed(callee, paren, arguments))
+        Ok(Call::boxed(callee, paren, arguments, named))
     }
 
     fn primary(&mut self) -> ExprResult {
@@ -348,6 +733,13 @@ impl<'p> Parser<'p> {
                 LoxString(s) => Ok(Literal::boxed(Rc::new(s))),
                 _ => Err("not a number or string".into()),
             }
+        } else if self.matches(&[Interpolation(Default::default())]) {
+            self.interpolation()
+        } else if self.matches(&[Fun]) {
+            let keyword = self.previous();
+            self.consume(&LeftParen, "Expect '(' after 'fun'.")?;
+            let (parameters, body) = self.function_body("function")?;
+            Ok(Lambda::boxed(keyword, parameters, body))
         } else if self.matches(&[Super]) {
             let keyword = self.previous();
             self.consume(&Dot, "Expect '.' after 'super'.")?;
@@ -360,6 +752,31 @@ impl<'p> Parser<'p> {
             Ok(This::boxed(self.previous()))
         } else if self.matches(&[Identifier(Default::default())]) {
             Ok(Variable::boxed(self.previous()))
+        } else if self.matches(&[LeftBracket]) {
+            let mut elements = vec![];
+            // A trailing comma is allowed after the last element.
+            while !self.check(&RightBracket) {
+                elements.push(self.assignment()?);
+                if !self.matches(&[Comma]) {
+                    break;
+                }
+            }
+            self.consume(&RightBracket, "Expect ']' after list elements.")?;
+            Ok(List::boxed(elements))
+        } else if self.matches(&[LeftBrace]) {
+            let brace = self.previous();
+            let mut entries = vec![];
+            // A trailing comma is allowed after the last entry.
+            while !self.check(&RightBrace) {
+                let key = self.assignment()?;
+                self.consume(&Colon, "Expect ':' after map key.")?;
+                entries.push((key, self.assignment()?));
+                if !self.matches(&[Comma]) {
+                    break;
+                }
+            }
+            self.consume(&RightBrace, "Expect '}' after map entries.")?;
+            Ok(Map::boxed(brace, entries))
         } else if self.matches(&[LeftParen]) {
             let expr = self.expression()?;
             self.consume(&RightParen, "Expect ')' after expression.")?;
@@ -369,6 +786,36 @@ impl<'p> Parser<'p> {
         }
     }
 
+    /// Desugars `"a ${b} c"` into `"a " + str(b) + " c"`, where `str` stands
+    /// for a Stringify expression.
+    fn interpolation(&mut self) -> ExprResult {
+        let mut expr: Box<dyn Expr> = Literal::boxed(Rc::new(String::new()));
+        loop {
+            let part = self.previous();
+            let plus = Token::new(Plus, "+".to_string(), part.line, part.span);
+            match &part.token_type {
+                Interpolation(s) | InterpolationEnd(s) if !s.is_empty() => {
+                    let s = s.clone();
+                    expr = Binary::boxed(expr, plus.clone(), Literal::boxed(Rc::new(s)));
+                }
+                _ => (),
+            }
+            if let InterpolationEnd(_) = part.token_type {
+                return Ok(expr);
+            }
+            let value = self.expression()?;
+            expr = Binary::boxed(expr, plus, Stringify::boxed(part, value));
+            let parts = [
+                Interpolation(Default::default()),
+                InterpolationEnd(Default::default()),
+            ];
+            if !self.matches(&parts) {
+                let message = "Expect '}' after interpolated expression.";
+                return Err(self.error(&self.peek(), message));
+            }
+        }
+    }
+
     fn matches(&mut self, types: &[TokenType]) -> bool {
         for t in types {
             if self.check(t) {
@@ -395,6 +842,13 @@ impl<'p> Parser<'p> {
         }
     }
 
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => discriminant(&token.token_type) == discriminant(token_type),
+            None => false,
+        }
+    }
+
     fn advance(&mut self) -> Token {
         if !self.is_at_end() {
             self.current += 1;
@@ -415,14 +869,8 @@ impl<'p> Parser<'p> {
         self.tokens[self.current - 1].clone()
     }
 
-    // TODO: create new error enum variant to include detailed information.
     fn error(&self, token: &Token, message: &str) -> Error {
-        let error_message = format!(
-            "[line {}] Error at {}: {}",
-            token.line, token.lexeme, message
-        );
-        eprintln!("{}", error_message);
-        error_message.into()
+        Error::parse(token, message)
     }
 
     fn synchronize(&mut self) {
@@ -432,10 +880,106 @@ impl<'p> Parser<'p> {
                 return;
             }
             match self.peek().token_type {
-                Class | Fun | Var | For | If | While | Print | Return => return,
+                Class | Const | Fun | Var | For | If | Import | Match | While | Print | Return
+                | Throw | Try => return,
                 _ => (),
             }
             self.advance();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    /// Returns the messages of the parse errors in `source`.
+    fn parse_errors(source: &str) -> Vec<String> {
+        let mut on_error = |_, _, message: &str| panic!("scan error: {}", message);
+        let tokens = Scanner::new(source, 0, &mut on_error)
+            .scan_tokens()
+            .to_vec();
+        let (_, errors) = Parser::new(&tokens).parse();
+        errors.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn recovers_at_try_and_throw() {
+        // Each statement has its own error, so none may be skipped over.
+        let errors = parse_errors("print 1 try { print 2 } finally {} throw 3 print 4;");
+        assert_eq!(
+            errors,
+            [
+                "Expect ';' after value.",
+                "Expect ';' after value.",
+                "Expect ';' after thrown value.",
+            ]
+        );
+    }
+
+    #[test]
+    fn recovers_at_import() {
+        let errors = parse_errors("print 1 2 import 3; print 4");
+        assert_eq!(
+            errors,
+            [
+                "Expect ';' after value.",
+                "Expect module path.",
+                "Expect ';' after value.",
+            ]
+        );
+    }
+
+    #[test]
+    fn recovers_at_const() {
+        let errors = parse_errors("print 1 2 const x; print 3");
+        assert_eq!(
+            errors,
+            [
+                "Expect ';' after value.",
+                "Expect '=' after constant name.",
+                "Expect ';' after value.",
+            ]
+        );
+    }
+
+    #[test]
+    fn multiple_declarators() {
+        let message = "Can't declare multiple variables in one 'var' statement.";
+        assert_eq!(parse_errors("var a = 1, b = 2;"), [message]);
+        assert_eq!(parse_errors("var a, b;"), [message]);
+        assert!(parse_errors("var a = (1, 2);").is_empty());
+        assert_eq!(
+            parse_errors("const a = 1, b = 2;"),
+            ["Can't declare multiple constants in one 'const' statement."]
+        );
+    }
+
+    #[test]
+    fn interpolation() {
+        assert!(parse_errors(r#"print "a${1 + 2}b${"c${3}"}d";"#).is_empty());
+        assert!(parse_errors(r#"print "${x}";"#).is_empty());
+    }
+
+    #[test]
+    fn interpolation_missing_operand() {
+        for source in [r#""${1 +}x" "y";"#, r#""${a +}x" "";"#, r#""${1}x${2 +}";"#] {
+            assert_eq!(parse_errors(source), ["Expect expression."], "{}", source);
+        }
+    }
+
+    #[test]
+    fn interpolation_empty_expression() {
+        assert_eq!(parse_errors(r#"print "${}";"#), ["Expect expression."]);
+    }
+
+    #[test]
+    fn interpolation_end_is_not_an_operand() {
+        // The text after `}` must not continue the interpolated expression.
+        assert_eq!(
+            parse_errors(r#"print "${1 "x"}";"#),
+            ["Expect '}' after interpolated expression."]
+        );
+    }
+}
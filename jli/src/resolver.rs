@@ -1,52 +1,75 @@
+use crate::error::Error;
 use crate::expr::{
-    self, Assign, Binary, Call, Expr, Get, Grouping, Literal, Logical, Set, Super, This, Unary,
-    Variable,
+    self, Assign, Binary, Call, Comma, Conditional, Expr, Get, Grouping, Index, Lambda, List,
+    Literal, Logical, Map, Set, SetIndex, Stringify, Super, This, Unary, Variable,
 };
-use crate::interpreter::Interpreter;
 use crate::object::Nil;
-use crate::stmt::{self, Block, Class, Expression, Function, If, Print, Return, Stmt, Var, While};
+use crate::stmt::{
+    self, Block, Break, Class, Const, Continue, Expression, ForIn, Function, If, Import, Match,
+    Parameters, Pattern, Print, Return, Stmt, Throw, Try, Var, While,
+};
 use crate::token::Token;
 use crate::Result;
-use std::cell::RefCell;
 use std::collections::HashMap;
+use std::mem;
 use std::ops::Deref;
 use std::rc::Rc;
 
+/// How many scopes out each local variable reference is, keyed by the
+/// address of the expression that refers to it.
+pub type Locals = HashMap<usize, usize>;
+
+#[derive(Default)]
 pub struct Resolver {
-    interpreter: Rc<RefCell<Interpreter>>,
-    scopes: Vec<HashMap<String, bool>>,
+    locals: Locals,
+    scopes: Vec<HashMap<String, Binding>>,
     current_function: FunctionType,
     current_class: ClassType,
+    in_loop: bool,
+    in_static_method: bool,
+    errors: Vec<Error>,
     nil: Rc<Nil>,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
+enum Binding {
+    Declared,
+    Defined,
+    Constant,
+}
+
+#[derive(Copy, Clone, Default)]
 enum FunctionType {
+    #[default]
     None,
     Function,
     Initializer,
     Method,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Default)]
 enum ClassType {
+    #[default]
     None,
     Class,
     Subclass,
 }
 
 impl Resolver {
-    pub fn new(interpreter: Rc<RefCell<Interpreter>>) -> Self {
-        Self {
-            interpreter,
-            scopes: vec![],
-            current_function: FunctionType::None,
-            current_class: ClassType::None,
-            nil: Rc::new(Nil),
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Resolves a whole program, carrying on after static errors so that all
+    /// of them can be reported at once.
+    pub fn resolve_program(&mut self, statements: &[Box<dyn Stmt>]) -> (Locals, Vec<Error>) {
+        if let Err(e) = self.resolve(statements) {
+            self.errors.push(e);
         }
+        (mem::take(&mut self.locals), mem::take(&mut self.errors))
     }
 
-    pub fn resolve<S>(&mut self, statements: &[S]) -> Result<()>
+    fn resolve<S>(&mut self, statements: &[S]) -> Result<()>
     where
         S: Deref<Target = dyn Stmt>,
     {
@@ -64,20 +87,57 @@ impl Resolver {
         stmt.accept(self)
     }
 
-    fn resolve_function(&mut self, function: &Function, function_type: FunctionType) -> Result<()> {
+    fn resolve_function(
+        &mut self,
+        params: &Parameters,
+        body: &[Rc<dyn Stmt>],
+        function_type: FunctionType,
+    ) -> Result<()> {
         let enclosing_function = self.current_function;
         self.current_function = function_type;
+        // Loops don't reach into the functions declared inside them.
+        let enclosing_loop = mem::replace(&mut self.in_loop, false);
         self.begin_scope();
-        for param in &function.params {
-            self.declare(param)?;
-            self.define(param);
+        for param in &params.positional {
+            // A default sees the parameters before it, but not its own.
+            if let Some(default) = &param.default {
+                self.resolve_expr(&**default)?;
+            }
+            self.declare(&param.name);
+            self.define(&param.name);
         }
-        self.resolve(function.body.as_slice())?;
+        if let Some(rest) = &params.rest {
+            self.declare(rest);
+            self.define(rest);
+        }
+        self.resolve(body)?;
         self.end_scope();
+        self.in_loop = enclosing_loop;
         self.current_function = enclosing_function;
         Ok(())
     }
 
+    fn resolve_pattern(&mut self, pattern: &Pattern, alternatives: bool) -> Result<()> {
+        match pattern {
+            Pattern::Binding(name) => {
+                // Only some of the alternatives might bind it.
+                if alternatives {
+                    self.error(name, "Can't bind names in a case with several patterns.");
+                }
+                self.declare(name);
+                self.define(name);
+            }
+            Pattern::Class(class, fields) => {
+                self.resolve_expr(class)?;
+                for (_, field) in fields {
+                    self.resolve_pattern(field, alternatives)?;
+                }
+            }
+            Pattern::Wildcard | Pattern::Literal(_) | Pattern::Range(..) => (),
+        }
+        Ok(())
+    }
+
     fn begin_scope(&mut self) {
         self.scopes.push(HashMap::new());
     }
@@ -86,28 +146,37 @@ impl Resolver {
         self.scopes.pop();
     }
 
-    fn declare(&mut self, name: &Token) -> Result<()> {
+    fn declare(&mut self, name: &Token) {
         if let Some(scope) = self.scopes.last_mut() {
-            if scope.contains_key(&name.lexeme) {
-                return Err("Already a variable with this name in this scope.".into());
+            let already_declared = scope.contains_key(&name.lexeme);
+            scope.insert(name.lexeme.clone(), Binding::Declared);
+            if already_declared {
+                self.error(name, "Already a variable with this name in this scope.");
             }
-            scope.insert(name.lexeme.clone(), false);
         }
-        Ok(())
     }
 
     fn define(&mut self, name: &Token) {
         if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(name.lexeme.clone(), true);
+            scope.insert(name.lexeme.clone(), Binding::Defined);
         }
     }
 
-    fn resolve_local(&self, expr: &dyn Expr, name: &Token) {
+    fn define_constant(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), Binding::Constant);
+        }
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        self.errors.push(Error::resolve(token, message));
+    }
+
+    fn resolve_local(&mut self, expr: &dyn Expr, name: &Token) {
         for (i, scope) in self.scopes.iter().enumerate().rev() {
             if scope.contains_key(&name.lexeme) {
-                self.interpreter
-                    .borrow_mut()
-                    .resolve(expr, self.scopes.len() - 1 - i);
+                let expr_ptr = expr as *const dyn Expr as *const () as usize;
+                self.locals.insert(expr_ptr, self.scopes.len() - 1 - i);
                 return;
             }
         }
@@ -117,6 +186,15 @@ impl Resolver {
 impl expr::Visitor<expr::VisitorResult> for Resolver {
     fn visit_assign_expr(&mut self, expr: &Assign) -> expr::VisitorResult {
         self.resolve_expr(&*expr.value)?;
+        // Globals aren't tracked here, so the interpreter checks those.
+        let binding = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&expr.name.lexeme));
+        if binding == Some(&Binding::Constant) {
+            self.error(&expr.name, "Cannot assign to constant.");
+        }
         self.resolve_local(expr, &expr.name);
         Ok(self.nil.clone())
     }
@@ -132,6 +210,22 @@ impl expr::Visitor<expr::VisitorResult> for Resolver {
         for argument in &expr.arguments {
             self.resolve_expr(&**argument)?;
         }
+        for (_, argument) in &expr.named {
+            self.resolve_expr(&**argument)?;
+        }
+        Ok(self.nil.clone())
+    }
+
+    fn visit_comma_expr(&mut self, expr: &Comma) -> expr::VisitorResult {
+        self.resolve_expr(&*expr.left)?;
+        self.resolve_expr(&*expr.right)?;
+        Ok(self.nil.clone())
+    }
+
+    fn visit_conditional_expr(&mut self, expr: &Conditional) -> expr::VisitorResult {
+        self.resolve_expr(&*expr.condition)?;
+        self.resolve_expr(&*expr.then_branch)?;
+        self.resolve_expr(&*expr.else_branch)?;
         Ok(self.nil.clone())
     }
 
@@ -145,6 +239,24 @@ impl expr::Visitor<expr::VisitorResult> for Resolver {
         Ok(self.nil.clone())
     }
 
+    fn visit_index_expr(&mut self, expr: &Index) -> expr::VisitorResult {
+        self.resolve_expr(&*expr.object)?;
+        self.resolve_expr(&*expr.index)?;
+        Ok(self.nil.clone())
+    }
+
+    fn visit_lambda_expr(&mut self, expr: &Lambda) -> expr::VisitorResult {
+        self.resolve_function(&expr.params, &expr.body, FunctionType::Function)?;
+        Ok(self.nil.clone())
+    }
+
+    fn visit_list_expr(&mut self, expr: &List) -> expr::VisitorResult {
+        for element in &expr.elements {
+            self.resolve_expr(&**element)?;
+        }
+        Ok(self.nil.clone())
+    }
+
     fn visit_literal_expr(&mut self, _expr: &Literal) -> expr::VisitorResult {
         Ok(self.nil.clone())
     }
@@ -155,25 +267,55 @@ impl expr::Visitor<expr::VisitorResult> for Resolver {
         Ok(self.nil.clone())
     }
 
+    fn visit_map_expr(&mut self, expr: &Map) -> expr::VisitorResult {
+        for (key, value) in &expr.entries {
+            self.resolve_expr(&**key)?;
+            self.resolve_expr(&**value)?;
+        }
+        Ok(self.nil.clone())
+    }
+
     fn visit_set_expr(&mut self, expr: &Set) -> expr::VisitorResult {
         self.resolve_expr(&*expr.value)?;
         self.resolve_expr(&*expr.object)?;
         Ok(self.nil.clone())
     }
 
+    fn visit_setindex_expr(&mut self, expr: &SetIndex) -> expr::VisitorResult {
+        self.resolve_expr(&*expr.value)?;
+        self.resolve_expr(&*expr.object)?;
+        self.resolve_expr(&*expr.index)?;
+        Ok(self.nil.clone())
+    }
+
+    fn visit_stringify_expr(&mut self, expr: &Stringify) -> expr::VisitorResult {
+        self.resolve_expr(&*expr.expression)?;
+        Ok(self.nil.clone())
+    }
+
     fn visit_super_expr(&mut self, expr: &Super) -> expr::VisitorResult {
         match self.current_class {
-            ClassType::None => return Err("Can't use 'super' outside of a class.".into()),
+            ClassType::None => self.error(&expr.keyword, "Can't use 'super' outside of a class."),
+            _ if self.in_static_method => {
+                self.error(&expr.keyword, "Can't use 'super' in a static method.")
+            }
             ClassType::Subclass => (),
-            _ => return Err("Can't use 'super' in a class with no superclass.".into()),
+            _ => self.error(
+                &expr.keyword,
+                "Can't use 'super' in a class with no superclass.",
+            ),
         }
         self.resolve_local(expr, &expr.keyword);
         Ok(self.nil.clone())
     }
 
     fn visit_this_expr(&mut self, expr: &This) -> expr::VisitorResult {
-        if let ClassType::None = self.current_class {
-            return Err("Can't use 'this' outside of a class.".into());
+        match self.current_class {
+            ClassType::None => self.error(&expr.keyword, "Can't use 'this' outside of a class."),
+            _ if self.in_static_method => {
+                self.error(&expr.keyword, "Can't use 'this' in a static method.")
+            }
+            _ => (),
         }
         self.resolve_local(expr, &expr.keyword);
         Ok(self.nil.clone())
@@ -185,10 +327,15 @@ impl expr::Visitor<expr::VisitorResult> for Resolver {
     }
 
     fn visit_variable_expr(&mut self, expr: &Variable) -> expr::VisitorResult {
-        if let Some(scope) = self.scopes.last() {
-            if scope.get(&expr.name.lexeme) == Some(&false) {
-                return Err("Can't read local variable in its own initializer.".into());
-            }
+        let in_own_initializer = self
+            .scopes
+            .last()
+            .is_some_and(|scope| scope.get(&expr.name.lexeme) == Some(&Binding::Declared));
+        if in_own_initializer {
+            self.error(
+                &expr.name,
+                "Can't read local variable in its own initializer.",
+            );
         }
         self.resolve_local(expr, &expr.name);
         Ok(self.nil.clone())
@@ -203,52 +350,97 @@ impl stmt::Visitor<stmt::VisitorResult> for Resolver {
         Ok(())
     }
 
+    fn visit_break_stmt(&mut self, stmt: &Break) -> stmt::VisitorResult {
+        if !self.in_loop {
+            self.error(&stmt.keyword, "Can't use 'break' outside of a loop.");
+        }
+        Ok(())
+    }
+
     fn visit_class_stmt(&mut self, stmt: &Class) -> stmt::VisitorResult {
         let enclosing_class = self.current_class;
         self.current_class = ClassType::Class;
-        self.declare(&stmt.name)?;
+        // A class declared inside a static method has instances of its own.
+        let enclosing_static_method = mem::replace(&mut self.in_static_method, false);
+        self.declare(&stmt.name);
         self.define(&stmt.name);
         if let Some(superclass) = &stmt.superclass {
             self.current_class = ClassType::Subclass;
             if stmt.name.lexeme == superclass.name.lexeme {
-                return Err("A class can't inherit from itself.".into());
+                self.error(&superclass.name, "A class can't inherit from itself.");
             }
             self.resolve_expr(superclass)?;
             self.begin_scope();
             self.scopes
                 .last_mut()
                 .expect("Just added a scope")
-                .insert("super".into(), true);
+                .insert("super".into(), Binding::Defined);
+        }
+        // Static methods have no `this`, so they close over the scope outside it.
+        self.in_static_method = true;
+        for method in &stmt.class_methods {
+            self.resolve_function(&method.params, &method.body, FunctionType::Method)?;
         }
+        self.in_static_method = false;
         self.begin_scope();
         self.scopes
             .last_mut()
             .expect("Just added a scope")
-            .insert("this".into(), true);
+            .insert("this".into(), Binding::Defined);
+        for method in stmt.getters.iter().chain(&stmt.setters) {
+            self.resolve_function(&method.params, &method.body, FunctionType::Method)?;
+        }
         for method in &stmt.methods {
             let declaration = if method.name.lexeme == "init" {
                 FunctionType::Initializer
             } else {
                 FunctionType::Method
             };
-            self.resolve_function(method, declaration)?;
+            self.resolve_function(&method.params, &method.body, declaration)?;
         }
         self.end_scope();
         if stmt.superclass.is_some() {
             self.end_scope();
         }
+        self.in_static_method = enclosing_static_method;
         self.current_class = enclosing_class;
         Ok(())
     }
 
+    fn visit_const_stmt(&mut self, stmt: &Const) -> stmt::VisitorResult {
+        self.declare(&stmt.name);
+        self.resolve_expr(&*stmt.initializer)?;
+        self.define_constant(&stmt.name);
+        Ok(())
+    }
+
+    fn visit_continue_stmt(&mut self, stmt: &Continue) -> stmt::VisitorResult {
+        if !self.in_loop {
+            self.error(&stmt.keyword, "Can't use 'continue' outside of a loop.");
+        }
+        Ok(())
+    }
+
     fn visit_expression_stmt(&mut self, stmt: &Expression) -> stmt::VisitorResult {
         self.resolve_expr(&*stmt.expression)
     }
 
+    fn visit_forin_stmt(&mut self, stmt: &ForIn) -> stmt::VisitorResult {
+        self.resolve_expr(&*stmt.iterable)?;
+        self.begin_scope();
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+        let enclosing_loop = mem::replace(&mut self.in_loop, true);
+        let result = self.resolve_stmt(&*stmt.body);
+        self.in_loop = enclosing_loop;
+        self.end_scope();
+        result
+    }
+
     fn visit_function_stmt(&mut self, stmt: &Function) -> stmt::VisitorResult {
-        self.declare(&stmt.name)?;
+        self.declare(&stmt.name);
         self.define(&stmt.name);
-        self.resolve_function(stmt, FunctionType::Function)
+        self.resolve_function(&stmt.params, &stmt.body, FunctionType::Function)
     }
 
     fn visit_if_stmt(&mut self, stmt: &If) -> stmt::VisitorResult {
@@ -260,25 +452,82 @@ impl stmt::Visitor<stmt::VisitorResult> for Resolver {
         Ok(())
     }
 
+    fn visit_import_stmt(&mut self, stmt: &Import) -> stmt::VisitorResult {
+        for name in stmt.alias.iter().chain(&stmt.names) {
+            self.declare(name);
+            self.define(name);
+        }
+        Ok(())
+    }
+
+    fn visit_match_stmt(&mut self, stmt: &Match) -> stmt::VisitorResult {
+        self.resolve_expr(&*stmt.subject)?;
+        let mut after_wildcard = false;
+        for case in &stmt.cases {
+            if after_wildcard {
+                self.error(&case.keyword, "Unreachable case after a wildcard.");
+            }
+            let catches_all = case
+                .patterns
+                .iter()
+                .any(|pattern| matches!(pattern, Pattern::Wildcard | Pattern::Binding(_)));
+            after_wildcard |= catches_all && case.guard.is_none();
+            self.begin_scope();
+            let alternatives = case.patterns.len() > 1;
+            for pattern in &case.patterns {
+                self.resolve_pattern(pattern, alternatives)?;
+            }
+            if let Some(guard) = &case.guard {
+                self.resolve_expr(&**guard)?;
+            }
+            self.resolve_stmt(&*case.body)?;
+            self.end_scope();
+        }
+        Ok(())
+    }
+
     fn visit_print_stmt(&mut self, stmt: &Print) -> stmt::VisitorResult {
         self.resolve_expr(&*stmt.expression)
     }
 
     fn visit_return_stmt(&mut self, stmt: &Return) -> stmt::VisitorResult {
         if let FunctionType::None = self.current_function {
-            return Err("Can't return from top-level code.".into());
+            self.error(&stmt.keyword, "Can't return from top-level code.");
         }
         if let Some(v) = &stmt.value {
             if let FunctionType::Initializer = self.current_function {
-                return Err("Can't return a value from an initializer.".into());
+                self.error(&stmt.keyword, "Can't return a value from an initializer.");
             }
             self.resolve_expr(&**v)?;
         }
         Ok(())
     }
 
+    fn visit_throw_stmt(&mut self, stmt: &Throw) -> stmt::VisitorResult {
+        self.resolve_expr(&*stmt.value)
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &Try) -> stmt::VisitorResult {
+        self.begin_scope();
+        self.resolve(&stmt.body)?;
+        self.end_scope();
+        if let Some((name, body)) = &stmt.catch {
+            self.begin_scope();
+            self.declare(name);
+            self.define(name);
+            self.resolve(body)?;
+            self.end_scope();
+        }
+        if let Some(body) = &stmt.finally {
+            self.begin_scope();
+            self.resolve(body)?;
+            self.end_scope();
+        }
+        Ok(())
+    }
+
     fn visit_var_stmt(&mut self, stmt: &Var) -> stmt::VisitorResult {
-        self.declare(&stmt.name)?;
+        self.declare(&stmt.name);
         if let Some(init) = &stmt.initializer {
             self.resolve_expr(&**init)?;
         }
@@ -288,6 +537,12 @@ impl stmt::Visitor<stmt::VisitorResult> for Resolver {
 
     fn visit_while_stmt(&mut self, stmt: &While) -> stmt::VisitorResult {
         self.resolve_expr(&*stmt.condition)?;
-        self.resolve_stmt(&*stmt.body)
+        if let Some(increment) = &stmt.increment {
+            self.resolve_expr(&**increment)?;
+        }
+        let enclosing_loop = mem::replace(&mut self.in_loop, true);
+        let result = self.resolve_stmt(&*stmt.body);
+        self.in_loop = enclosing_loop;
+        result
     }
 }
@@ -1,23 +1,38 @@
-use crate::token::{Token, TokenType};
+use crate::token::{Span, Token, TokenType};
+use std::str::Chars;
+
+type OnError<'s> = &'s mut dyn FnMut(usize, Span, &str);
 
 pub struct Scanner<'s> {
-    source: String,
-    on_error: &'s mut dyn FnMut(usize, &'s str),
+    source: &'s str,
+    source_id: usize,
+    on_error: OnError<'s>,
     tokens: Vec<Token>,
+    // Byte offsets into source, always on char boundaries.
     start: usize,
     current: usize,
     line: usize,
+    column: usize,
+    start_line: usize,
+    start_column: usize,
+    // Brace depth inside each `${...}` we are scanning, innermost last.
+    interpolations: Vec<usize>,
 }
 
 impl<'s> Scanner<'s> {
-    pub fn new(source: String, on_error: &'s mut dyn FnMut(usize, &'s str)) -> Self {
+    pub fn new(source: &'s str, source_id: usize, on_error: OnError<'s>) -> Self {
         Self {
             source,
+            source_id,
             on_error,
             tokens: vec![],
             start: 0,
             current: 0,
             line: 1,
+            column: 1,
+            start_line: 1,
+            start_column: 1,
+            interpolations: vec![],
         }
     }
 
@@ -25,10 +40,19 @@ impl<'s> Scanner<'s> {
         while !self.is_at_end() {
             // We are at the beginning of the next lexeme.
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column;
             self.scan_token();
         }
+        if !self.interpolations.is_empty() {
+            self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column;
+            self.error("Unterminated string.");
+        }
+        let span = Span::new(self.source_id, self.column, self.current, self.current);
         self.tokens
-            .push(Token::new(TokenType::Eof, "".to_string(), self.line));
+            .push(Token::new(TokenType::Eof, "".to_string(), self.line, span));
         &self.tokens
     }
 
@@ -38,20 +62,97 @@ impl<'s> Scanner<'s> {
         match c {
             '(' => self.add_token(LeftParen),
             ')' => self.add_token(RightParen),
-            '{' => self.add_token(LeftBrace),
-            '}' => self.add_token(RightBrace),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token(LeftBrace);
+            }
+            '}' => match self.interpolations.last_mut() {
+                // This closes a `${`, so the string carries on.
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.string(true);
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    self.add_token(RightBrace);
+                }
+                None => self.add_token(RightBrace),
+            },
+            ':' => self.add_token(Colon),
             ',' => self.add_token(Comma),
-            '.' => self.add_token(Dot),
-            '-' => self.add_token(Minus),
-            '+' => self.add_token(Plus),
+            '.' => {
+                let token = if !self.matches('.') {
+                    Dot
+                } else if self.matches('.') {
+                    DotDotDot
+                } else {
+                    DotDot
+                };
+                self.add_token(token);
+            }
+            '-' => {
+                let token = if self.matches('-') {
+                    MinusMinus
+                } else if self.matches('=') {
+                    MinusEqual
+                } else {
+                    Minus
+                };
+                self.add_token(token);
+            }
+            '+' => {
+                let token = if self.matches('+') {
+                    PlusPlus
+                } else if self.matches('=') {
+                    PlusEqual
+                } else {
+                    Plus
+                };
+                self.add_token(token);
+            }
+            '[' => self.add_token(LeftBracket),
+            ']' => self.add_token(RightBracket),
+            '?' => self.add_token(Question),
             ';' => self.add_token(Semicolon),
-            '*' => self.add_token(Star),
+            '*' => {
+                let token = if self.matches('*') {
+                    StarStar
+                } else if self.matches('=') {
+                    StarEqual
+                } else {
+                    Star
+                };
+                self.add_token(token);
+            }
+            '~' => {
+                if self.matches('/') {
+                    self.add_token(TildeSlash);
+                } else {
+                    self.error("Unexpected character.");
+                }
+            }
+            '%' => {
+                let token = if self.matches('=') {
+                    PercentEqual
+                } else {
+                    Percent
+                };
+                self.add_token(token);
+            }
             '!' => {
                 let token = if self.matches('=') { BangEqual } else { Bang };
                 self.add_token(token);
             }
             '=' => {
-                let token = if self.matches('=') { EqualEqual } else { Equal };
+                let token = if self.matches('=') {
+                    EqualEqual
+                } else if self.matches('>') {
+                    EqualGreater
+                } else {
+                    Equal
+                };
                 self.add_token(token);
             }
             '<' => {
@@ -71,31 +172,52 @@ impl<'s> Scanner<'s> {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                } else if self.matches('*') {
+                    self.block_comment();
+                } else if self.matches('=') {
+                    self.add_token(SlashEqual);
                 } else {
                     self.add_token(Slash);
                 }
             }
             // Ignore whitespace.
-            ' ' | '\r' | '\t' => (),
-            '\n' => self.line += 1,
-            '"' => self.string(),
+            ' ' | '\r' | '\t' | '\n' => (),
+            '"' => self.string(false),
             _ => {
                 if self.is_digit(c) {
                     self.number();
                 } else if self.is_alpha(c) {
                     self.identifier();
                 } else {
-                    (self.on_error)(self.line, "Unexpected character.");
+                    self.error("Unexpected character.");
                 }
             }
         }
     }
 
+    /// Skips a `/* ... */` comment, which may contain nested ones.
+    fn block_comment(&mut self) {
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                self.error("Unterminated block comment.");
+                return;
+            }
+            if self.peek() == '/' && self.peek_next() == '*' {
+                self.advance();
+                depth += 1;
+            } else if self.peek() == '*' && self.peek_next() == '/' {
+                self.advance();
+                depth -= 1;
+            }
+            self.advance();
+        }
+    }
+
     fn identifier(&mut self) {
         while self.is_alphanumeric(self.peek()) {
             self.advance();
         }
-        // TODO: use .get or .is_char_boundary to avoid issues with UTF-8 chars.
         let text = &self.source[self.start..self.current];
         let token_type = match self.keyword(text) {
             Some(t) => t,
@@ -108,19 +230,30 @@ impl<'s> Scanner<'s> {
         use TokenType::*;
         match text {
             "and" => Some(And),
+            "break" => Some(Break),
+            "case" => Some(Case),
+            "catch" => Some(Catch),
             "class" => Some(Class),
+            "const" => Some(Const),
+            "continue" => Some(Continue),
             "else" => Some(Else),
             "false" => Some(False),
+            "finally" => Some(Finally),
             "for" => Some(For),
             "fun" => Some(Fun),
             "if" => Some(If),
+            "import" => Some(Import),
+            "in" => Some(In),
+            "match" => Some(Match),
             "nil" => Some(Nil),
             "or" => Some(Or),
             "print" => Some(Print),
             "return" => Some(Return),
             "super" => Some(Super),
             "this" => Some(This),
+            "throw" => Some(Throw),
             "true" => Some(True),
+            "try" => Some(Try),
             "var" => Some(Var),
             "while" => Some(While),
             _ => None,
@@ -128,64 +261,197 @@ impl<'s> Scanner<'s> {
     }
 
     fn number(&mut self) {
-        while self.is_digit(self.peek()) {
-            self.advance();
+        let leading_zero = &self.source[self.start..self.current] == "0";
+        let literal = match self.peek() {
+            'x' | 'X' if leading_zero => self.radix_number(16),
+            'b' | 'B' if leading_zero => self.radix_number(2),
+            _ => self.decimal_number(),
+        };
+        match literal {
+            Ok(n) => self.add_token(TokenType::Number(n)),
+            Err(message) => {
+                self.error(&message);
+                // Keep a token so the parser doesn't report a second error.
+                self.add_token(TokenType::Number(0.0));
+            }
         }
+    }
+
+    fn decimal_number(&mut self) -> Result<f64, String> {
+        self.digits(10);
         // Look for a fractional part.
         if self.peek() == '.' && self.is_digit(self.peek_next()) {
             // Consume the "."
             self.advance();
-            while self.is_digit(self.peek()) {
+            self.digits(10);
+        }
+        // Look for an exponent.
+        if matches!(self.peek(), 'e' | 'E') {
+            self.advance();
+            if matches!(self.peek(), '+' | '-') {
                 self.advance();
             }
+            if !self.is_digit(self.peek()) {
+                return Err("Expect digits in exponent.".to_string());
+            }
+            self.digits(10);
         }
-        // TODO: get rid of unwraps if possible.
-        let literal: f64 = self.source[self.start..self.current].parse().unwrap();
-        self.add_token(TokenType::Number(literal));
+        let text = &self.source[self.start..self.current];
+        check_separators(text, 10)?;
+        text.replace('_', "")
+            .parse()
+            .map_err(|_| "Malformed number literal.".to_string())
     }
 
-    fn string(&mut self) {
-        while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+    /// Scans the digits of a `0x` or `0b` literal, the prefix's letter being
+    /// the next character.
+    fn radix_number(&mut self, radix: u32) -> Result<f64, String> {
+        let prefix = format!("0{}", self.advance());
+        let digits_start = self.current;
+        // Take in any trailing letters too so that "0b102" is one bad literal.
+        while self.is_alphanumeric(self.peek()) {
+            self.advance();
+        }
+        let digits = &self.source[digits_start..self.current];
+        if digits.is_empty() {
+            return Err(format!("Expect digits after '{}'.", prefix));
+        }
+        check_separators(digits, radix)?;
+        let mut value = 0.0;
+        for c in digits.chars().filter(|&c| c != '_') {
+            match c.to_digit(radix) {
+                Some(d) => value = value * radix as f64 + d as f64,
+                None => return Err(format!("Invalid digit '{}' in '{}' literal.", c, prefix)),
             }
+        }
+        Ok(value)
+    }
+
+    /// Consumes a run of digits in the given radix and '_' separators.
+    fn digits(&mut self, radix: u32) {
+        while self.peek().is_digit(radix) || self.peek() == '_' {
             self.advance();
         }
+    }
+
+    /// Scans a string literal, or what follows an interpolated expression
+    /// when `continued`.
+    fn string(&mut self, continued: bool) {
+        let mut value = String::new();
+        while self.peek() != '"' && !self.is_at_end() {
+            if self.peek() == '\\' {
+                if let Some(c) = self.escape() {
+                    value.push(c);
+                }
+            } else if self.peek() == '$' && self.peek_next() == '{' {
+                self.advance();
+                self.advance();
+                // The expression's tokens come next, then the rest of the
+                // string once the matching '}' is found.
+                self.interpolations.push(0);
+                self.add_token(TokenType::Interpolation(value));
+                return;
+            } else {
+                value.push(self.advance());
+            }
+        }
         if self.is_at_end() {
-            (self.on_error)(self.line, "Unterminated string.");
+            self.error("Unterminated string.");
+            // Any strings this one is interpolated into are unterminated too.
+            self.interpolations.clear();
             return;
         }
         // The closing ".
         self.advance();
-        // Trim the surrounding quotes.
-        let value = self.source[(self.start + 1)..(self.current - 1)].to_string();
-        self.add_token(TokenType::LoxString(value));
+        if continued {
+            self.add_token(TokenType::InterpolationEnd(value));
+        } else {
+            self.add_token(TokenType::LoxString(value));
+        }
+    }
+
+    /// Decodes the escape sequence starting at the current backslash,
+    /// reporting an error spanning the sequence if it is malformed.
+    fn escape(&mut self) -> Option<char> {
+        let line = self.line;
+        let column = self.column;
+        let start = self.current;
+        // The backslash.
+        self.advance();
+        let result = match self.peek() {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '"' => Ok('"'),
+            '$' => Ok('$'),
+            '\\' => Ok('\\'),
+            'u' => {
+                self.advance();
+                self.unicode_escape()
+            }
+            _ => Err("Invalid escape sequence."),
+        };
+        // Leave an unterminated string for string() to report.
+        if !self.is_at_end() && (result.is_ok() || self.peek() != '"') {
+            self.advance();
+        }
+        result
+            .map_err(|message| {
+                let span = Span::new(self.source_id, column, start, self.current);
+                (self.on_error)(line, span, message);
+            })
+            .ok()
+    }
+
+    /// Decodes the `{XXXX}` part of a `\u{XXXX}` escape, leaving the closing
+    /// brace to be consumed by the caller.
+    fn unicode_escape(&mut self) -> Result<char, &'static str> {
+        if self.peek() != '{' {
+            return Err("Expect '{' after '\\u'.");
+        }
+        self.advance();
+        let digits_start = self.current;
+        while self.peek().is_ascii_hexdigit() {
+            self.advance();
+        }
+        let digits = &self.source[digits_start..self.current];
+        if self.peek() != '}' {
+            return Err("Expect '}' after Unicode escape.");
+        }
+        if digits.is_empty() || digits.len() > 6 {
+            return Err("Unicode escape must have 1 to 6 hex digits.");
+        }
+        u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or("Invalid Unicode code point.")
     }
 
     fn matches(&mut self, expected: char) -> bool {
-        if self.is_at_end() || self.source.chars().nth(self.current).unwrap() != expected {
+        if self.is_at_end() || self.peek() != expected {
             false
         } else {
-            self.current += 1;
+            self.advance();
             true
         }
     }
 
     // TODO: maybe return Option<char> instead?
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            '\0'
-        } else {
-            self.source.chars().nth(self.current).unwrap()
-        }
+        self.rest().next().unwrap_or('\0')
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            '\0'
-        } else {
-            self.source.chars().nth(self.current + 1).unwrap()
-        }
+        let mut rest = self.rest();
+        rest.next();
+        rest.next().unwrap_or('\0')
+    }
+
+    /// The characters not consumed yet. `current` is a byte offset that
+    /// always sits on a char boundary, so this never panics.
+    fn rest(&self) -> Chars<'s> {
+        self.source[self.current..].chars()
     }
 
     fn is_alpha(&self, c: char) -> bool {
@@ -197,7 +463,7 @@ impl<'s> Scanner<'s> {
     }
 
     fn is_digit(&self, c: char) -> bool {
-        c.is_digit(10)
+        c.is_ascii_digit()
     }
 
     fn is_at_end(&self) -> bool {
@@ -205,12 +471,119 @@ impl<'s> Scanner<'s> {
     }
 
     fn advance(&mut self) -> char {
-        self.current += 1;
-        self.source.chars().nth(self.current - 1).unwrap()
+        let c = self.peek();
+        self.current += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        c
     }
 
     fn add_token(&mut self, token_type: TokenType) {
         let text = self.source[self.start..self.current].to_string();
-        self.tokens.push(Token::new(token_type, text, self.line));
+        let span = self.span();
+        self.tokens
+            .push(Token::new(token_type, text, self.start_line, span));
+    }
+
+    fn span(&self) -> Span {
+        Span::new(self.source_id, self.start_column, self.start, self.current)
+    }
+
+    /// Reports an error spanning the current lexeme.
+    fn error(&mut self, message: &str) {
+        let span = self.span();
+        (self.on_error)(self.start_line, span, message);
+    }
+}
+
+/// Checks that every '_' in a number literal sits between two digits.
+fn check_separators(text: &str, radix: u32) -> Result<(), String> {
+    let chars: Vec<char> = text.chars().collect();
+    for (i, c) in chars.iter().enumerate() {
+        if *c != '_' {
+            continue;
+        }
+        let before = i.checked_sub(1).and_then(|j| chars.get(j));
+        let after = chars.get(i + 1);
+        let is_digit = |c: Option<&char>| c.is_some_and(|c| c.is_digit(radix));
+        if !is_digit(before) || !is_digit(after) {
+            return Err("Digit separators must be between digits.".to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::TokenType::*;
+
+    fn scan(source: &str) -> (Vec<Token>, Vec<String>) {
+        let mut errors = vec![];
+        let mut on_error = |_: usize, _: Span, message: &str| errors.push(message.to_string());
+        let tokens = Scanner::new(source, 0, &mut on_error)
+            .scan_tokens()
+            .to_vec();
+        (tokens, errors)
+    }
+
+    #[test]
+    fn strings_keep_multibyte_text() {
+        let (tokens, errors) = scan(r#""héllo wörld" "🦀 + 🐍 = ❤️""#);
+        assert!(errors.is_empty());
+        assert_eq!(tokens[0].token_type, LoxString("héllo wörld".into()));
+        assert_eq!(tokens[1].token_type, LoxString("🦀 + 🐍 = ❤️".into()));
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        let (tokens, errors) = scan(r#""\u{1F980} \u{e9}" "\u{41}\u{10FFFF}""#);
+        assert!(errors.is_empty());
+        assert_eq!(tokens[0].token_type, LoxString("🦀 é".into()));
+        assert_eq!(tokens[1].token_type, LoxString("A\u{10FFFF}".into()));
+    }
+
+    #[test]
+    fn invalid_unicode_escapes() {
+        for source in [
+            r#""\u{}""#,
+            r#""\u{1234567}""#,
+            r#""\u{D800}""#,
+            r#""\u{zz}""#,
+        ] {
+            let (_, errors) = scan(source);
+            assert_eq!(errors.len(), 1, "{}", source);
+        }
+    }
+
+    #[test]
+    fn columns_count_characters_after_non_ascii() {
+        let (tokens, errors) = scan("\"é🦀\" x\n// ¿qué? 🦀\n  y");
+        assert!(errors.is_empty());
+        let x = &tokens[1];
+        assert_eq!(x.lexeme, "x");
+        // `"é🦀"` is 4 characters but 8 bytes.
+        assert_eq!((x.line, x.span.column), (1, 6));
+        assert_eq!((x.span.start, x.span.end), (9, 10));
+        let y = &tokens[2];
+        assert_eq!((y.line, y.span.column), (3, 3));
+        assert_eq!(
+            &"\"é🦀\" x\n// ¿qué? 🦀\n  y"[y.span.start..y.span.end],
+            "y"
+        );
+    }
+
+    #[test]
+    fn string_spans_cover_multibyte_characters() {
+        let source = "var s = \"naïve\";";
+        let (tokens, _) = scan(source);
+        let string = &tokens[3];
+        assert_eq!(&source[string.span.start..string.span.end], "\"naïve\"");
+        assert_eq!(string.span.column, 9);
+        assert_eq!(tokens[4].span.column, 16);
     }
 }
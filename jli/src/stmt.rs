@@ -1,19 +1,83 @@
 use crate::ast;
 use crate::expr::{Expr, Variable};
+use crate::object::Object;
 use crate::token::Token;
 use crate::Result;
 use std::rc::Rc;
 
 pub type VisitorResult = Result<()>;
 
+/// A parameter list: `(a, b = 2, ...rest)`.
+#[derive(Clone, Debug, Default)]
+pub struct Parameters {
+    pub positional: Vec<Parameter>,
+    /// Collects any positional arguments past the others into a list.
+    pub rest: Option<Token>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Parameter {
+    pub name: Token,
+    pub default: Option<Rc<dyn Expr>>,
+}
+
+/// `case 1, 2 if guard => body`, which runs when any pattern matches and
+/// the guard, if present, is truthy.
+#[derive(Debug)]
+pub struct Case {
+    pub keyword: Token,
+    pub patterns: Vec<Pattern>,
+    pub guard: Option<Box<dyn Expr>>,
+    pub body: Box<dyn Stmt>,
+}
+
+#[derive(Debug)]
+pub enum Pattern {
+    /// `_` matches anything.
+    Wildcard,
+    /// A name matches anything and binds the value to it.
+    Binding(Token),
+    Literal(Rc<dyn Object>),
+    /// `low..high` matches numbers between both ends, inclusive.
+    Range(f64, f64),
+    /// `Point(x, y: 0)` matches instances of the class or its subclasses
+    /// whose fields match their patterns. A bare field name binds it.
+    Class(Variable, Vec<(Token, Pattern)>),
+}
+
 ast!(Stmt -> VisitorResult [
     Block(statements: Vec<Box<dyn Stmt>>),
-    Class(name: Token, superclass: Option<Variable>, methods: Vec<Rc<Function>>),
+    Break(keyword: Token),
+    // Getters have no parameters and setters have exactly one.
+    Class(
+        name: Token,
+        superclass: Option<Variable>,
+        methods: Vec<Rc<Function>>,
+        class_methods: Vec<Rc<Function>>,
+        getters: Vec<Rc<Function>>,
+        setters: Vec<Rc<Function>>
+    ),
+    Const(name: Token, initializer: Box<dyn Expr>),
+    Continue(keyword: Token),
     Expression(expression: Box<dyn Expr>),
-    Function(name: Token, params: Vec<Token>, body: Vec<Rc<dyn Stmt>>),
+    // Declares `name` afresh for each element that `keyword` (`in`) yields.
+    ForIn(name: Token, keyword: Token, iterable: Box<dyn Expr>, body: Box<dyn Stmt>),
+    Function(name: Token, params: Parameters, body: Vec<Rc<dyn Stmt>>),
+    // Binds the module to `alias` and/or its definitions to `names`.
+    Import(path: Token, alias: Option<Token>, names: Vec<Token>),
     If(condition: Box<dyn Expr>, then_branch: Box<dyn Stmt>, else_branch: Option<Box<dyn Stmt>>),
-    Print(expression: Box<dyn Expr>),
+    // Runs the first case that matches `subject`, if any.
+    Match(subject: Box<dyn Expr>, cases: Vec<Case>),
+    Print(keyword: Token, expression: Box<dyn Expr>),
     Return(keyword: Token, value: Option<Box<dyn Expr>>),
+    Throw(keyword: Token, value: Box<dyn Expr>),
+    // The catch clause binds the caught value to its token.
+    Try(
+        body: Vec<Box<dyn Stmt>>,
+        catch: Option<(Token, Vec<Box<dyn Stmt>>)>,
+        finally: Option<Vec<Box<dyn Stmt>>>
+    ),
     Var(name: Token, initializer: Option<Box<dyn Expr>>),
-    While(condition: Box<dyn Expr>, body: Box<dyn Stmt>),
+    // The increment of a desugared for loop runs after `continue` too.
+    While(condition: Box<dyn Expr>, body: Box<dyn Stmt>, increment: Option<Box<dyn Expr>>),
 ]);
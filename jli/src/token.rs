@@ -5,14 +5,40 @@ pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub line: usize,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, line: usize) -> Self {
+    pub fn new(token_type: TokenType, lexeme: String, line: usize, span: Span) -> Self {
         Self {
             token_type,
             lexeme,
             line,
+            span,
+        }
+    }
+}
+
+/// Where a lexeme sits in the source.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Span {
+    /// 1-based column, in characters, of the first character.
+    pub column: usize,
+    /// Byte offset of the first character.
+    pub start: usize,
+    /// Byte offset just past the last character.
+    pub end: usize,
+    /// Which of the interpreter's sources the offsets refer to.
+    pub source: usize,
+}
+
+impl Span {
+    pub fn new(source: usize, column: usize, start: usize, end: usize) -> Self {
+        Self {
+            column,
+            start,
+            end,
+            source,
         }
     }
 }
@@ -27,21 +53,32 @@ impl fmt::Display for Token {
 #[derive(Clone, Debug, PartialEq)]
 pub enum TokenType {
     // Single-character tokens.
-    LeftParen, RightParen, LeftBrace, RightBrace,
-    Comma, Dot, Minus, Plus, Semicolon, Slash, Star,
+    LeftParen, RightParen, LeftBrace, RightBrace, LeftBracket, RightBracket,
+    Colon, Comma, Question, Semicolon,
 
     // One or two character tokens.
     Bang, BangEqual,
-    Equal, EqualEqual,
+    Dot, DotDot, DotDotDot,
+    Equal, EqualEqual, EqualGreater,
     Greater, GreaterEqual,
     Less, LessEqual,
+    Minus, MinusEqual, MinusMinus,
+    Percent, PercentEqual,
+    Plus, PlusEqual, PlusPlus,
+    Slash, SlashEqual,
+    Star, StarEqual, StarStar,
+    TildeSlash,
 
     // Literals.
     Identifier(String), LoxString(String), Number(f64),
+    // A string part followed by an interpolated expression.
+    Interpolation(String),
+    // The rest of a string after its last interpolated expression.
+    InterpolationEnd(String),
 
     // Keywords.
-    And, Class, Else, False, Fun, For, If, Nil, Or,
-    Print, Return, Super, This, True, Var, While,
+    And, Break, Case, Catch, Class, Const, Continue, Else, False, Finally, Fun, For, If, Import,
+    In, Match, Nil, Or, Print, Return, Super, This, Throw, True, Try, Var, While,
 
     Eof,
 }